@@ -1,6 +1,12 @@
+pub mod bit_grid;
 pub mod dense_grid;
 pub mod grid;
+pub mod hashlife;
+pub mod lenia_grid;
 pub mod render;
 pub mod rle;
 pub mod rule;
+pub mod rule_table;
+pub mod sparse_grid;
+pub mod turmite;
 pub mod universe;
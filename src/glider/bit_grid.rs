@@ -0,0 +1,283 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use super::grid::{Grid, RowCol, Topology};
+use super::rule::Rule;
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Stores 64 cells per word, and computes all of them at once with bitwise adders.
+#[derive(Clone, Debug)]
+pub struct BitGrid {
+    // One dead row above and below the grid. Bits past the last column are always dead.
+    words: Vec<u64>,
+    words_per_row: usize,
+    nb_rows: usize,
+    nb_cols: usize,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+fn half_adder(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let (s, c1) = half_adder(a, b);
+    let (s, c2) = half_adder(s, c);
+
+    (s, c1 | c2)
+}
+
+/// Bit-sliced sum of 8 neighbour words: the returned words are the 4 bits of each count.
+fn count_neighbours(n: [u64; 8]) -> [u64; 4] {
+    let (s0, c0) = full_adder(n[0], n[1], n[2]);
+    let (s1, c1) = full_adder(n[3], n[4], n[5]);
+    let (s2, c2) = half_adder(n[6], n[7]);
+
+    let (ones, c3) = full_adder(s0, s1, s2);
+    let (t, c4) = full_adder(c0, c1, c2);
+    let (twos, c5) = half_adder(t, c3);
+    let (fours, eights) = half_adder(c4, c5);
+
+    [ones, twos, fours, eights]
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl BitGrid {
+    // Rows including the dead border.
+    fn padded_row(&self, row: usize) -> &[u64] {
+        let start = row * self.words_per_row;
+        &self.words[start..start + self.words_per_row]
+    }
+
+    fn last_word_mask(&self) -> u64 {
+        match self.nb_cols % 64 {
+            0 => !0,
+            n => (1 << n) - 1,
+        }
+    }
+
+    // Word and bit of a cell in the grid's bounds.
+    fn index(&self, rc: RowCol) -> Option<(usize, u64)> {
+        if (0..self.nb_rows as i64).contains(&rc.row) && (0..self.nb_cols as i64).contains(&rc.col)
+        {
+            let (row, col) = (rc.row as usize, rc.col as usize);
+            Some(((row + 1) * self.words_per_row + col / 64, 1 << (col % 64)))
+        } else {
+            None
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Grid for BitGrid {
+    fn new(nb_rows: usize, nb_cols: usize) -> Self {
+        let words_per_row = nb_cols.div_ceil(64);
+
+        BitGrid {
+            words: vec![0; (nb_rows + 2) * words_per_row],
+            words_per_row,
+            nb_rows,
+            nb_cols,
+        }
+    }
+
+    fn at(&self, rc: RowCol) -> bool {
+        match self.index(rc) {
+            Some((index, bit)) => self.words[index] & bit != 0,
+            None => false,
+        }
+    }
+
+    fn set(&mut self, rc: RowCol, value: bool) {
+        match self.index(rc) {
+            Some((index, bit)) if value => self.words[index] |= bit,
+            Some((index, bit)) => self.words[index] &= !bit,
+            None => {}
+        }
+    }
+
+    fn count_live_neighbours(&self, rc: RowCol) -> u8 {
+        let mut count = 0;
+
+        for row in rc.row - 1..=rc.row + 1 {
+            for col in rc.col - 1..=rc.col + 1 {
+                if (row, col) != (rc.row, rc.col) && self.at(RowCol { row, col }) {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    fn count_live_cells(&self) -> u64 {
+        self.words.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    fn nb_columns(&self) -> usize {
+        self.nb_cols
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(Topology::Plane)
+    }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        let rows = self
+            .words
+            .chunks(self.words_per_row)
+            .skip(1)
+            .take(self.nb_rows);
+
+        Box::new(rows.enumerate().flat_map(|(row, words)| {
+            words.iter().enumerate().flat_map(move |(w, &word)| {
+                // Pops the lowest bit until the word is empty.
+                let non_zero = |word: u64| Some(word).filter(|&w| w != 0);
+                std::iter::successors(non_zero(word), move |&word| non_zero(word & (word - 1))).map(
+                    move |word| RowCol {
+                        row: row as i64,
+                        col: (w * 64 + word.trailing_zeros() as usize) as i64,
+                    },
+                )
+            })
+        }))
+    }
+
+    fn clear_like(&mut self, other: &Self) {
+        if self.words.len() == other.words.len() && self.words_per_row == other.words_per_row {
+            self.words.fill(0);
+            self.nb_rows = other.nb_rows;
+            self.nb_cols = other.nb_cols;
+        } else {
+            *self = other.empty_like();
+        }
+    }
+
+    fn next_generation(&self, rule: &Rule, next: &mut Self, _nb_threads: usize) -> bool {
+        // Bit-sliced counts don't tell where neighbours are.
+        let life = match rule {
+            Rule::LifeLike(life) if life.is_totalistic() => life,
+            _ => return false,
+        };
+
+        // For each number of neighbours, the outcome for dead and live cells.
+        let outcomes: Vec<_> = (0..=8)
+            .map(|n| (n, life.lives(false, n), life.lives(true, n)))
+            .filter(|&(_, birth, survival)| birth || survival)
+            .collect();
+
+        let last_word_mask = self.last_word_mask();
+
+        for row in 0..self.nb_rows {
+            let above = self.padded_row(row);
+            let current = self.padded_row(row + 1);
+            let below = self.padded_row(row + 2);
+
+            for w in 0..self.words_per_row {
+                let west = |r: &[u64]| (r[w] << 1) | w.checked_sub(1).map_or(0, |p| r[p] >> 63);
+                let east = |r: &[u64]| (r[w] >> 1) | r.get(w + 1).map_or(0, |n| n << 63);
+
+                let count = count_neighbours([
+                    west(above),
+                    above[w],
+                    east(above),
+                    west(current),
+                    east(current),
+                    west(below),
+                    below[w],
+                    east(below),
+                ]);
+
+                let alive = current[w];
+                let mut word = 0;
+                for &(n, birth, survival) in &outcomes {
+                    let matches = (0..4).fold(!0, |acc, bit| {
+                        acc & if n & (1 << bit) != 0 {
+                            count[bit]
+                        } else {
+                            !count[bit]
+                        }
+                    });
+                    let state = match (birth, survival) {
+                        (true, true) => !0,
+                        (true, false) => !alive,
+                        (false, true) => alive,
+                        (false, false) => 0,
+                    };
+                    word |= matches & state;
+                }
+
+                if w + 1 == self.words_per_row {
+                    word &= last_word_mask;
+                }
+                next.words[(row + 1) * self.words_per_row + w] = word;
+            }
+        }
+
+        true
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::glider::dense_grid::DenseGrid;
+    use crate::glider::grid::test::random_grid;
+    use crate::glider::universe::Universe;
+
+    #[test]
+    fn test_count_neighbours() {
+        for n in 0..=8u8 {
+            let inputs = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| if i < n { 1 } else { 0 });
+            let count = count_neighbours(inputs);
+
+            assert_eq!(
+                count[0] + 2 * count[1] + 4 * count[2] + 8 * count[3],
+                n as u64
+            );
+        }
+    }
+
+    #[test]
+    fn test_next_generation() {
+        let (rows, cols) = (70, 130);
+        let dense = random_grid::<DenseGrid>(42, rows, cols).with_topology(Topology::Plane);
+        let bits: BitGrid = random_grid(42, rows, cols);
+
+        for rule in [
+            Rule::new(vec![3], vec![2, 3]),
+            Rule::new(vec![3, 6], vec![2, 3]),
+            Rule::new(vec![1], vec![0, 8]),
+        ] {
+            let mut dense = Universe::new(dense.clone(), rule.clone());
+            let mut bits = Universe::new(bits.clone(), rule);
+
+            for _ in 0..30 {
+                dense.step();
+                bits.step();
+
+                assert_eq!(dense.live_cells, bits.live_cells);
+                assert_eq!(dense.grid.bounding_box(), bits.grid.bounding_box());
+                assert!(bits.grid.live_cells().all(|rc| dense.grid.at(rc)));
+                for row in 0..rows as i64 {
+                    for col in 0..cols as i64 {
+                        assert_eq!(dense.at(row, col), bits.at(row, col));
+                    }
+                }
+            }
+        }
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
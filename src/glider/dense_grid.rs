@@ -1,53 +1,490 @@
 /* --------------------------------------------------------------------------------------------- */
 
-use super::grid::{Grid, RowCol};
-use super::rle::{Rle, RleEntry};
+use std::ops::Range;
+
+use super::grid::{Grid, RowCol, Topology, NEIGHBOURS};
+use super::rule::{LargerThanLife, Rule, Shape, Wolfram};
 
 /* --------------------------------------------------------------------------------------------- */
 
+// Side of the square tiles of which changes are tracked.
+const TILE_SIZE: usize = 32;
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Without a topology, the grid is unbounded: it grows whenever a live cell comes close to its
+/// borders. Otherwise, it's bounded to its initial dimensions.
 #[derive(Clone, Debug)]
 pub struct DenseGrid {
-    grid: Vec<Vec<bool>>,
+    // States of cells (see `Grid::state`), surrounded by a dead border.
+    grid: Vec<Vec<u8>>,
+    // Position of the first cell of `grid` after the border.
+    origin: RowCol,
+    // For each tile, whether it changed at the last generation. Tiles which didn't change, as well
+    // as their neighbours, won't change at the next generation.
+    changed: Vec<bool>,
+    // Rule which computed this generation. Tiles can only sleep if the next one is computed with
+    // the same rule, which isn't the case when B0 rules are emulated.
+    rule: Option<Rule>,
     nb_rows: usize,
     nb_cols: usize,
+    topology: Option<Topology>,
 }
 
 /* --------------------------------------------------------------------------------------------- */
 
 impl DenseGrid {
-    pub fn new_from_rle(rle: &Rle, rows: usize, columns: usize) -> Self {
-        let (rle_rows, rle_cols) = rle.dimension();
-        let rows = usize::max(rows, rle_rows);
-        let columns = usize::max(columns, rle_cols);
+    /// Bounds the grid to its initial dimensions. Cells outside, if any, are lost.
+    pub fn with_topology(self, topology: Topology) -> Self {
+        let mut grid = DenseGrid {
+            topology: Some(topology),
+            ..Self::new(self.nb_rows, self.nb_cols)
+        };
+
+        for row in 0..self.nb_rows as i64 {
+            for col in 0..self.nb_cols as i64 {
+                grid.set_state(RowCol { row, col }, self.state(RowCol { row, col }));
+            }
+        }
+
+        grid
+    }
+
+    fn storage_rows(&self) -> usize {
+        self.grid.len() - 2
+    }
+
+    fn storage_cols(&self) -> usize {
+        self.grid[0].len() - 2
+    }
+
+    // Position in `grid` of a cell in the grid's storage.
+    fn index(&self, rc: RowCol) -> Option<(usize, usize)> {
+        let row = rc.row - self.origin.row;
+        let col = rc.col - self.origin.col;
+
+        if (0..self.storage_rows() as i64).contains(&row)
+            && (0..self.storage_cols() as i64).contains(&col)
+        {
+            Some((row as usize + 1, col as usize + 1))
+        } else {
+            None
+        }
+    }
+
+    // Whether a cell is at least `margin` cells away from the storage's edges.
+    fn is_inside(&self, rc: RowCol, margin: i64) -> bool {
+        let row = rc.row - self.origin.row;
+        let col = rc.col - self.origin.col;
+
+        row >= margin
+            && col >= margin
+            && row + margin < self.storage_rows() as i64
+            && col + margin < self.storage_cols() as i64
+    }
+
+    // Reallocates a bigger storage, with the current one at its centre, until a cell is at least
+    // `margin` cells away from the edges.
+    fn grow(&mut self, rc: RowCol, margin: i64) {
+        let mut rows = self.storage_rows();
+        let mut cols = self.storage_cols();
+        let mut origin = self.origin;
+
+        while rc.row < origin.row + margin || rc.row + margin >= origin.row + rows as i64 {
+            origin.row -= (rows / 2 + 2) as i64;
+            rows = 2 * rows + 4;
+        }
+        while rc.col < origin.col + margin || rc.col + margin >= origin.col + cols as i64 {
+            origin.col -= (cols / 2 + 2) as i64;
+            cols = 2 * cols + 4;
+        }
+
+        let mut grid = vec![vec![0; cols + 2]; rows + 2];
+        let row_shift = (self.origin.row - origin.row) as usize;
+        let col_shift = (self.origin.col - origin.col) as usize;
+
+        for (row, cells) in self
+            .grid
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(self.storage_rows())
+        {
+            grid[row + row_shift][col_shift + 1..col_shift + 1 + self.storage_cols()]
+                .copy_from_slice(&cells[1..=self.storage_cols()]);
+        }
+
+        self.grid = grid;
+        self.origin = origin;
+        self.changed = vec![true; self.tile_rows() * self.tile_cols()];
+    }
+
+    fn tile_rows(&self) -> usize {
+        self.storage_rows().div_ceil(TILE_SIZE)
+    }
 
-        let mut grid = Self::new(rows, columns);
+    fn tile_cols(&self) -> usize {
+        self.storage_cols().div_ceil(TILE_SIZE)
+    }
 
-        // TODO. RLE dimension might be bigger than the grid's.
-        let row_shift = rows / 2 - rle_rows / 2;
-        let col_shift = columns / 2 - rle_cols / 2;
+    // Tiles which might change at the next generation.
+    fn awake_tiles(&self, rule: &Rule) -> Vec<bool> {
+        let (tile_rows, tile_cols) = (self.tile_rows(), self.tile_cols());
 
-        let mut row = row_shift;
-        let mut col = col_shift;
+        // Empty tiles come to life with B0 rules.
+        if self.rule.as_ref() != Some(rule)
+            || matches!(rule, Rule::LifeLike(life) if life.lives(false, 0))
+        {
+            return vec![true; self.changed.len()];
+        }
 
-        for entry in &rle.pattern {
-            match entry {
-                RleEntry::Live(nb) => {
-                    for col in col..col + nb {
-                        grid.set(RowCol { row, col }, true);
+        let mut awake = vec![false; self.changed.len()];
+        for tile_row in 0..tile_rows {
+            for tile_col in 0..tile_cols {
+                if !self.changed[tile_row * tile_cols + tile_col] {
+                    continue;
+                }
+                for row in tile_row.saturating_sub(1)..usize::min(tile_row + 2, tile_rows) {
+                    for col in tile_col.saturating_sub(1)..usize::min(tile_col + 2, tile_cols) {
+                        awake[row * tile_cols + col] = true;
                     }
-                    col += nb;
                 }
-                RleEntry::Dead(nb) => {
-                    col += nb;
+
+                // Other topologies join the borders: the neighbours of tiles on a border are
+                // where the cells around them wrap.
+                let on_border = tile_row == 0
+                    || tile_col == 0
+                    || tile_row + 1 == tile_rows
+                    || tile_col + 1 == tile_cols;
+                let topology = match self.topology {
+                    Some(topology) if topology != Topology::Plane && on_border => topology,
+                    _ => continue,
+                };
+                let rows = (tile_row * TILE_SIZE) as i64 - 1
+                    ..=usize::min((tile_row + 1) * TILE_SIZE, self.nb_rows) as i64;
+                let cols = (tile_col * TILE_SIZE) as i64 - 1
+                    ..=usize::min((tile_col + 1) * TILE_SIZE, self.nb_cols) as i64;
+                let outside = |row: i64, col: i64| {
+                    row < 0 || col < 0 || row >= self.nb_rows as i64 || col >= self.nb_cols as i64
+                };
+
+                for row in rows.clone() {
+                    for col in cols.clone() {
+                        let on_ring = row == *rows.start()
+                            || row == *rows.end()
+                            || col == *cols.start()
+                            || col == *cols.end();
+                        if !on_ring || !outside(row, col) {
+                            continue;
+                        }
+                        if let Some((row, col)) =
+                            topology.wrap(self.nb_rows, self.nb_cols, row, col)
+                        {
+                            awake[row / TILE_SIZE * tile_cols + col / TILE_SIZE] = true;
+                        }
+                    }
                 }
-                RleEntry::NewRow(nb) => {
-                    row += nb;
-                    col = col_shift;
+            }
+        }
+
+        awake
+    }
+
+    // Computes the next generation of a band of tile rows, starting at `first_tile_row`, into
+    // `rows`. Returns whether each tile of the band changed.
+    fn next_band(
+        &self,
+        rule: &Rule,
+        awake: &[bool],
+        first_tile_row: usize,
+        rows: &mut [Vec<u8>],
+    ) -> Vec<bool> {
+        let tile_cols = self.tile_cols();
+        let mut changed = vec![];
+
+        for (i, tile) in rows.chunks_mut(TILE_SIZE).enumerate() {
+            let tile_row = first_tile_row + i;
+
+            for tile_col in 0..tile_cols {
+                let cols = tile_col * TILE_SIZE + 1
+                    ..usize::min((tile_col + 1) * TILE_SIZE, self.storage_cols()) + 1;
+                let mut tile_changed = false;
+
+                for (r, next_row) in tile.iter_mut().enumerate() {
+                    let row = tile_row * TILE_SIZE + r + 1;
+
+                    if !awake[tile_row * tile_cols + tile_col] {
+                        next_row[cols.clone()].copy_from_slice(&self.grid[row][cols.clone()]);
+                        continue;
+                    }
+
+                    for col in cols.clone() {
+                        let rc = RowCol {
+                            row: self.origin.row + row as i64 - 1,
+                            col: self.origin.col + col as i64 - 1,
+                        };
+                        let current = self.grid[row][col];
+                        let next = match rule {
+                            Rule::Table(rule_table) => {
+                                rule_table.next_state(current, &self.neighbour_states(rc))
+                            }
+                            Rule::LifeLike(life) if life.is_totalistic() => rule.next_state(
+                                current,
+                                life.lives(current == 1, self.count_live_neighbours(rc)),
+                            ),
+                            Rule::LifeLike(life) => rule.next_state(
+                                current,
+                                life.lives_in(current == 1, self.neighbourhood(rc)),
+                            ),
+                            _ => unreachable!("{} isn't computed tile by tile", rule),
+                        };
+
+                        next_row[col] = next;
+                        tile_changed |= next != current;
+                    }
                 }
-            };
+
+                changed.push(tile_changed);
+            }
         }
 
-        grid
+        changed
+    }
+
+    // Cells which aren't dead at most `margin` cells away from the storage's edges, to grow it if
+    // they are too close.
+    fn extreme_live_cells(&self, margin: usize) -> Vec<RowCol> {
+        let (rows, cols) = (self.storage_rows(), self.storage_cols());
+        let mut cells = vec![];
+
+        for row in (1..=rows).filter(|&r| r <= margin || r + margin > rows) {
+            if let Some(col) = self.grid[row].iter().position(|&c| c != 0) {
+                cells.push((row, col));
+            }
+        }
+        for (row, cells_row) in self.grid.iter().enumerate() {
+            for col in (1..=cols).filter(|&c| c <= margin || c + margin > cols) {
+                if cells_row[col] != 0 {
+                    cells.push((row, col));
+                }
+            }
+        }
+
+        cells
+            .into_iter()
+            .map(|(row, col)| RowCol {
+                row: self.origin.row + row as i64 - 1,
+                col: self.origin.col + col as i64 - 1,
+            })
+            .collect()
+    }
+
+    // Summed-area table of the storage extended by `radius` cells on each side: entry (r, c) is the
+    // number of live cells in the r first rows and c first columns. Cells beyond the storage are
+    // where the topology tells.
+    fn summed_area_table(&self, radius: usize) -> Vec<Vec<u32>> {
+        let (rows, cols) = (self.storage_rows(), self.storage_cols());
+        let alive = |row: usize, col: usize| {
+            self.neighbour_at_border(
+                self.origin.row + row as i64 - radius as i64,
+                self.origin.col + col as i64 - radius as i64,
+            )
+        };
+
+        let mut table = vec![vec![0; cols + 2 * radius + 1]; rows + 2 * radius + 1];
+        for row in 0..rows + 2 * radius {
+            let mut row_sum = 0;
+            for col in 0..cols + 2 * radius {
+                row_sum += alive(row, col) as u32;
+                table[row + 1][col + 1] = table[row][col + 1] + row_sum;
+            }
+        }
+
+        table
+    }
+
+    // Computes the next generation of the storage rows from `first_row` into `rows`, for a Larger
+    // than Life rule, with `table` from `summed_area_table`.
+    fn next_band_ltl(
+        &self,
+        rule: &Rule,
+        ltl: &LargerThanLife,
+        table: &[Vec<u32>],
+        first_row: usize,
+        rows: &mut [Vec<u8>],
+    ) {
+        let radius = ltl.radius as usize;
+        // Live cells in the rectangle of the extended storage from (r0, c0) to (r1, c1) included.
+        let sum = |r0: usize, c0: usize, r1: usize, c1: usize| {
+            table[r1 + 1][c1 + 1] + table[r0][c0] - table[r0][c1 + 1] - table[r1 + 1][c0]
+        };
+
+        for (i, next_row) in rows.iter_mut().enumerate() {
+            let row = first_row + i;
+
+            for col in 0..self.storage_cols() {
+                let current = self.grid[row + 1][col + 1];
+                let mut count = match ltl.shape {
+                    Shape::Moore => sum(row, col, row + 2 * radius, col + 2 * radius),
+                    Shape::VonNeumann => (0..=2 * radius)
+                        .map(|dr| {
+                            let width = radius - dr.abs_diff(radius);
+                            sum(
+                                row + dr,
+                                col + radius - width,
+                                row + dr,
+                                col + radius + width,
+                            )
+                        })
+                        .sum(),
+                    // Row dr - radius spans columns from dr - radius to dr + radius around the
+                    // cell, within the square.
+                    Shape::Hexagonal => (0..=2 * radius)
+                        .map(|dr| {
+                            let first = dr.saturating_sub(radius);
+                            let last = usize::min(2 * radius, dr + radius);
+                            sum(row + dr, col + first, row + dr, col + last)
+                        })
+                        .sum(),
+                };
+                if !ltl.middle && current == 1 {
+                    count -= 1;
+                }
+
+                next_row[col + 1] = rule.next_state(current, ltl.lives(current == 1, count));
+            }
+        }
+    }
+
+    // Larger than Life rules count live cells with a summed-area table rather than tile by tile.
+    fn next_generation_ltl(
+        &self,
+        rule: &Rule,
+        ltl: &LargerThanLife,
+        next: &mut Self,
+        nb_threads: usize,
+    ) -> bool {
+        // Cells are born up to `radius` cells away from live ones.
+        let margin = ltl.radius as i64 + 1;
+        if self.topology.is_none() {
+            let too_close: Vec<_> = self
+                .extreme_live_cells(margin as usize)
+                .into_iter()
+                .filter(|&rc| !self.is_inside(rc, margin))
+                .collect();
+
+            if !too_close.is_empty() {
+                let mut grown = self.clone();
+                for rc in too_close {
+                    if !grown.is_inside(rc, margin) {
+                        grown.grow(rc, margin);
+                    }
+                }
+                next.clear_like(&grown);
+
+                return grown.next_generation_ltl(rule, ltl, next, nb_threads);
+            }
+        }
+
+        let table = self.summed_area_table(ltl.radius as usize);
+        let storage_rows = self.storage_rows();
+        let band_rows = usize::max(1, storage_rows.div_ceil(usize::max(1, nb_threads)));
+        let rows = &mut next.grid[1..=storage_rows];
+
+        if band_rows >= storage_rows {
+            self.next_band_ltl(rule, ltl, &table, 0, rows);
+        } else {
+            std::thread::scope(|scope| {
+                for (i, band) in rows.chunks_mut(band_rows).enumerate() {
+                    let table = &table;
+                    scope.spawn(move || self.next_band_ltl(rule, ltl, table, i * band_rows, band));
+                }
+            });
+        }
+        next.changed.fill(true);
+
+        true
+    }
+
+    // One-dimensional rules only compute the row below the last one, the others being kept. On
+    // bounded grids, the row wraps around as the topology tells.
+    fn next_generation_wolfram(&self, wolfram: &Wolfram, next: &mut Self) -> bool {
+        let wrap = |row, col| match self.topology {
+            None => Some(RowCol { row, col }),
+            Some(topology) => {
+                topology
+                    .wrap(self.nb_rows, self.nb_cols, row, col)
+                    .map(|(row, col)| RowCol {
+                        row: row as i64,
+                        col: col as i64,
+                    })
+            }
+        };
+        let row = wolfram.row();
+
+        if next.precedes(self) {
+            // Only the row computed at the last generation is missing.
+            for col in self.origin.col - 1..self.origin.col + self.storage_cols() as i64 + 1 {
+                if let Some(rc) = wrap(row - 1, col) {
+                    next.set_state(rc, self.state(rc));
+                }
+            }
+        } else {
+            next.clone_from(self);
+        }
+        next.changed.fill(true);
+        next.rule = Some(Rule::Wolfram(*wolfram));
+
+        let (_, cols) = self.active_area();
+        for col in cols.start - 1..cols.end + 1 {
+            let above = |dc| self.neighbour_state_at_border(row - 1, col + dc) == 1;
+            let state = wolfram.lives([above(-1), above(0), above(1)]) as u8;
+
+            // Dead cells beyond the storage of unbounded grids are left out rather than written.
+            match wrap(row, col) {
+                Some(rc) if state != 0 || next.index(rc).is_some() => next.set_state(rc, state),
+                _ => {}
+            }
+        }
+
+        true
+    }
+
+    // Whether this grid holds the generation of a one-dimensional rule before the one of `other`,
+    // in the same storage, so that they only differ by the row computed for `other`.
+    fn precedes(&self, other: &Self) -> bool {
+        let previous = match &other.rule {
+            Some(Rule::Wolfram(wolfram)) => Rule::Wolfram(wolfram.previous()),
+            _ => return false,
+        };
+
+        self.rule.as_ref() == Some(&previous)
+            && self.origin == other.origin
+            && self.topology == other.topology
+            && self.storage_rows() == other.storage_rows()
+            && self.storage_cols() == other.storage_cols()
+    }
+
+    // Neighbours of cells on the edges or outside the storage depend on the topology.
+    fn count_live_neighbours_at_border(&self, rc: RowCol) -> u8 {
+        NEIGHBOURS
+            .iter()
+            .filter(|&&(dr, dc)| self.neighbour_at_border(rc.row + dr, rc.col + dc))
+            .count() as u8
+    }
+
+    fn neighbour_at_border(&self, row: i64, col: i64) -> bool {
+        self.neighbour_state_at_border(row, col) == 1
+    }
+
+    fn neighbour_state_at_border(&self, row: i64, col: i64) -> u8 {
+        match self.topology {
+            None => self.state(RowCol { row, col }),
+            Some(topology) => match topology.wrap(self.nb_rows, self.nb_cols, row, col) {
+                Some((row, col)) => self.grid[row + 1][col + 1],
+                None => 0,
+            },
+        }
     }
 }
 
@@ -55,42 +492,119 @@ impl DenseGrid {
 
 impl Grid for DenseGrid {
     fn new(nb_rows: usize, nb_cols: usize) -> Self {
-        let mut row = Vec::new();
-        row.resize(nb_cols + 2, false);
+        let row = vec![0; nb_cols + 2];
 
         let mut grid = Vec::new();
         grid.resize(nb_rows + 2, row);
 
         DenseGrid {
             grid,
+            origin: RowCol { row: 0, col: 0 },
+            changed: vec![true; nb_rows.div_ceil(TILE_SIZE) * nb_cols.div_ceil(TILE_SIZE)],
+            rule: None,
             nb_cols,
             nb_rows,
+            topology: None,
+        }
+    }
+
+    fn empty_like(&self) -> Self {
+        DenseGrid {
+            grid: vec![vec![0; self.storage_cols() + 2]; self.storage_rows() + 2],
+            origin: self.origin,
+            changed: vec![true; self.changed.len()],
+            rule: None,
+            nb_rows: self.nb_rows,
+            nb_cols: self.nb_cols,
+            topology: self.topology,
         }
     }
 
     fn at(&self, rc: RowCol) -> bool {
-        self.grid[rc.row + 1][rc.col + 1]
+        self.state(rc) == 1
     }
 
     fn set(&mut self, rc: RowCol, value: bool) {
-        self.grid[rc.row + 1][rc.col + 1] = value;
+        self.set_state(rc, value as u8);
+    }
+
+    fn state(&self, rc: RowCol) -> u8 {
+        match self.index(rc) {
+            Some((row, col)) => self.grid[row][col],
+            None => 0,
+        }
+    }
+
+    fn set_state(&mut self, rc: RowCol, state: u8) {
+        if state != 0 && self.topology.is_none() && !self.is_inside(rc, 2) {
+            self.grow(rc, 2);
+        }
+
+        if let Some((row, col)) = self.index(rc) {
+            if self.grid[row][col] != state {
+                let tile = (row - 1) / TILE_SIZE * self.tile_cols() + (col - 1) / TILE_SIZE;
+                self.grid[row][col] = state;
+                self.changed[tile] = true;
+            }
+        }
     }
 
     fn count_live_neighbours(&self, rc: RowCol) -> u8 {
         debug_assert!(self.grid.len() > 2);
         debug_assert!(self.grid[0].len() > 2);
 
-        let x = rc.row + 1;
-        let y = rc.col + 1;
+        // The dead border is right for planes, but not for other topologies.
+        let fast = match self.topology {
+            None | Some(Topology::Plane) => self.index(rc),
+            Some(_) if self.is_inside(rc, 1) => self.index(rc),
+            Some(_) => None,
+        };
+
+        let (x, y) = match fast {
+            Some(xy) => xy,
+            None => return self.count_live_neighbours_at_border(rc),
+        };
+
+        let alive = |row: usize, col: usize| (self.grid[row][col] == 1) as u8;
 
-        self.grid[x - 1][y - 1] as u8
-            + self.grid[x - 1][y] as u8
-            + self.grid[x - 1][y + 1] as u8
-            + self.grid[x][y - 1] as u8
-            + self.grid[x][y + 1] as u8
-            + self.grid[x + 1][y - 1] as u8
-            + self.grid[x + 1][y] as u8
-            + self.grid[x + 1][y + 1] as u8
+        alive(x - 1, y - 1)
+            + alive(x - 1, y)
+            + alive(x - 1, y + 1)
+            + alive(x, y - 1)
+            + alive(x, y + 1)
+            + alive(x + 1, y - 1)
+            + alive(x + 1, y)
+            + alive(x + 1, y + 1)
+    }
+
+    fn neighbourhood(&self, rc: RowCol) -> u8 {
+        let inside = matches!(self.topology, None | Some(Topology::Plane)) || self.is_inside(rc, 1);
+
+        NEIGHBOURS
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, &(dr, dc))| {
+                let (row, col) = (rc.row + dr, rc.col + dc);
+                let alive = if inside {
+                    self.at(RowCol { row, col })
+                } else {
+                    self.neighbour_at_border(row, col)
+                };
+                acc | (alive as u8) << i
+            })
+    }
+
+    fn neighbour_states(&self, rc: RowCol) -> [u8; 8] {
+        let inside = matches!(self.topology, None | Some(Topology::Plane)) || self.is_inside(rc, 1);
+
+        NEIGHBOURS.map(|(dr, dc)| {
+            let (row, col) = (rc.row + dr, rc.col + dc);
+            if inside {
+                self.state(RowCol { row, col })
+            } else {
+                self.neighbour_state_at_border(row, col)
+            }
+        })
     }
 
     fn nb_rows(&self) -> usize {
@@ -101,11 +615,151 @@ impl Grid for DenseGrid {
         self.nb_cols
     }
 
+    fn topology(&self) -> Option<Topology> {
+        self.topology
+    }
+
     fn count_live_cells(&self) -> u64 {
         self.grid.iter().fold(0, |acc, col| {
-            acc + col.iter().fold(0, |acc, cell| acc + *cell as u64)
+            acc + col.iter().fold(0, |acc, &cell| acc + (cell == 1) as u64)
         })
     }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        Box::new(
+            self.cell_states()
+                .filter(|&(_, state)| state == 1)
+                .map(|(rc, _)| rc),
+        )
+    }
+
+    fn cell_states(&self) -> Box<dyn Iterator<Item = (RowCol, u8)> + '_> {
+        let origin = self.origin;
+
+        Box::new(self.grid.iter().enumerate().flat_map(move |(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|&(_, &state)| state != 0)
+                .map(move |(col, &state)| {
+                    let rc = RowCol {
+                        row: origin.row + row as i64 - 1,
+                        col: origin.col + col as i64 - 1,
+                    };
+                    (rc, state)
+                })
+        }))
+    }
+
+    fn bounding_box(&self) -> Option<(Range<i64>, Range<i64>)> {
+        let first_row = self.grid.iter().position(|cells| cells.contains(&1))?;
+        let last_row = self.grid.iter().rposition(|cells| cells.contains(&1))?;
+        let rows = &self.grid[first_row..=last_row];
+
+        let first_col = rows
+            .iter()
+            .filter_map(|cells| cells.iter().position(|&c| c == 1))
+            .min()?;
+        let last_col = rows
+            .iter()
+            .filter_map(|cells| cells.iter().rposition(|&c| c == 1))
+            .max()?;
+
+        let (row, col) = (self.origin.row - 1, self.origin.col - 1);
+        Some((
+            row + first_row as i64..row + last_row as i64 + 1,
+            col + first_col as i64..col + last_col as i64 + 1,
+        ))
+    }
+
+    // The generation before `other` of a one-dimensional rule is kept, as its next generation only
+    // adds a row to `other` (see `next_generation_wolfram`).
+    fn clear_like(&mut self, other: &Self) {
+        if self.precedes(other) {
+            return;
+        }
+        if self.grid.len() != other.grid.len() || self.grid[0].len() != other.grid[0].len() {
+            *self = other.empty_like();
+            return;
+        }
+
+        for row in &mut self.grid {
+            row.fill(0);
+        }
+        self.changed.clear();
+        self.changed.resize(other.changed.len(), true);
+        self.rule = None;
+        self.origin = other.origin;
+        self.nb_rows = other.nb_rows;
+        self.nb_cols = other.nb_cols;
+        self.topology = other.topology;
+    }
+
+    fn check_rule(&self, rule: &Rule) -> Result<(), &'static str> {
+        match rule {
+            Rule::Lenia(_) => Err("Lenia rules need a Lenia grid"),
+            _ => Ok(()),
+        }
+    }
+
+    fn next_generation(&self, rule: &Rule, next: &mut Self, nb_threads: usize) -> bool {
+        match rule {
+            Rule::LifeLike(_) | Rule::Table(_) => {}
+            Rule::LargerThanLife(ltl) => {
+                return self.next_generation_ltl(rule, ltl, next, nb_threads);
+            }
+            Rule::Wolfram(wolfram) => return self.next_generation_wolfram(wolfram, next),
+            // Cells of block rules depend on their position, and turmites and Lenia rules don't
+            // compute cells from their neighbours.
+            Rule::Margolus(_) | Rule::Turmite(_) | Rule::Lenia(_) => return false,
+        }
+
+        let awake = self.awake_tiles(rule);
+        let tile_rows = self.tile_rows();
+        let band_tiles = usize::max(1, tile_rows.div_ceil(usize::max(1, nb_threads)));
+
+        let storage_rows = self.storage_rows();
+        let rows = &mut next.grid[1..=storage_rows];
+
+        next.changed = if band_tiles >= tile_rows {
+            self.next_band(rule, &awake, 0, rows)
+        } else {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = rows
+                    .chunks_mut(band_tiles * TILE_SIZE)
+                    .enumerate()
+                    .map(|(i, band)| {
+                        let awake = &awake;
+                        scope.spawn(move || self.next_band(rule, awake, i * band_tiles, band))
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().unwrap())
+                    .collect()
+            })
+        };
+
+        next.rule = Some(rule.clone());
+
+        if next.topology.is_none() {
+            for rc in next.extreme_live_cells(2) {
+                if !next.is_inside(rc, 2) {
+                    next.grow(rc, 2);
+                }
+            }
+        }
+
+        true
+    }
+
+    fn active_area(&self) -> (Range<i64>, Range<i64>) {
+        (
+            self.origin.row..self.origin.row + self.storage_rows() as i64,
+            self.origin.col..self.origin.col + self.storage_cols() as i64,
+        )
+    }
 }
 
 /* --------------------------------------------------------------------------------------------- */
@@ -115,18 +769,28 @@ impl Grid for DenseGrid {
 mod test {
 
     use super::*;
+    use crate::glider::grid::test::random_grid;
+    use crate::glider::rle::{Rle, RleEntry};
+    use crate::glider::sparse_grid::SparseGrid;
+    use crate::glider::universe::Universe;
 
     impl DenseGrid {
-        pub fn new_from(g: &Vec<Vec<bool>>) -> Self {
-            assert!(g.len() >= 1);
-            assert!(g[0].len() >= 1);
+        pub fn new_from(g: &[Vec<bool>]) -> Self {
+            assert!(!g.is_empty());
+            assert!(!g[0].is_empty());
             // TODO. Check size consistency
 
             let mut grid = Self::new(g.len() + 2, g[0].len() + 2);
 
-            for row in 0..g.len() {
-                for col in 0..g[0].len() {
-                    grid.set(RowCol { row, col }, g[row][col]);
+            for (row, cells) in g.iter().enumerate() {
+                for (col, &cell) in cells.iter().enumerate() {
+                    grid.set(
+                        RowCol {
+                            row: row as i64,
+                            col: col as i64,
+                        },
+                        cell,
+                    );
                 }
             }
 
@@ -138,19 +802,19 @@ mod test {
     fn test_count_live_neighbours() {
         {
             // 1x1 universe
-            let g = DenseGrid::new_from(&vec![vec![false]]);
+            let g = DenseGrid::new_from(&[vec![false]]);
 
             assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 0);
         }
         {
             // 1x1 universe
-            let g = DenseGrid::new_from(&vec![vec![true]]);
+            let g = DenseGrid::new_from(&[vec![true]]);
 
             assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 0);
         }
         {
             // 2x2 universe
-            let g = DenseGrid::new_from(&vec![
+            let g = DenseGrid::new_from(&[
                 //   0       1
                 vec![true, false], // 0
                 vec![false, true], // 1
@@ -163,7 +827,7 @@ mod test {
         }
         {
             // 3x3 universe
-            let g = DenseGrid::new_from(&vec![
+            let g = DenseGrid::new_from(&[
                 //   0      1      2
                 vec![true, false, true],   // 0
                 vec![false, true, false],  // 1
@@ -179,6 +843,348 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_torus_count_live_neighbours() {
+        let mut g = DenseGrid::new(3, 4).with_topology(Topology::Torus);
+        g.set(RowCol { row: 0, col: 0 }, true);
+        g.set(RowCol { row: 2, col: 3 }, true);
+
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 1);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 0 }), 2);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 1, col: 3 }), 2);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 1, col: 1 }), 1);
+        assert_eq!(g.count_live_cells(), 2);
+
+        let g = g.with_topology(Topology::Plane);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 0);
+        assert_eq!(g.count_live_cells(), 2);
+    }
+
+    #[test]
+    fn test_torus_glider() {
+        let mut g = DenseGrid::new(8, 8).with_topology(Topology::Torus);
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        for &(row, col) in &glider {
+            g.set(RowCol { row, col }, true);
+        }
+
+        // A glider moves one cell diagonally every 4 generations.
+        let mut u = Universe::new(g, Rule::new(vec![3], vec![2, 3]));
+        for _ in 0..4 * 8 {
+            u.step();
+            assert_eq!(u.live_cells, 5);
+        }
+        for &(row, col) in &glider {
+            assert!(u.at(row, col));
+        }
+    }
+
+    #[test]
+    fn test_wrapped_sleeping_tiles() {
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        let topologies = [
+            Topology::Torus,
+            Topology::KleinBottle {
+                horizontal_twist: true,
+            },
+            Topology::KleinBottle {
+                horizontal_twist: false,
+            },
+            Topology::CrossSurface,
+            Topology::Sphere,
+        ];
+
+        for topology in topologies {
+            let mut g = DenseGrid::new(70, 75).with_topology(topology);
+            // Gliders in the corners, heading for the borders.
+            for (corner_row, corner_col) in [(3, 3), (3, 70), (65, 3), (65, 70)] {
+                for &(row, col) in &glider {
+                    let row = if corner_row < 35 { 2 - row } else { row };
+                    let col = if corner_col < 35 { 2 - col } else { col };
+                    g.set(
+                        RowCol {
+                            row: corner_row + row,
+                            col: corner_col + col,
+                        },
+                        true,
+                    );
+                }
+            }
+
+            let rule = Rule::new(vec![3], vec![2, 3]);
+            let mut u = Universe::new(g, rule.clone());
+            for _ in 0..100 {
+                // A new grid computes all its tiles.
+                let mut every_tile =
+                    Universe::new(u.grid.clone().with_topology(topology), rule.clone());
+                every_tile.step();
+                u.step();
+                for row in 0..70 {
+                    for col in 0..75 {
+                        assert_eq!(u.at(row, col), every_tile.at(row, col), "{:?}", topology);
+                    }
+                }
+            }
+            assert!(u.grid.changed.contains(&false));
+        }
+    }
+
+    #[test]
+    fn test_twisted_count_live_neighbours() {
+        let klein = |horizontal_twist| {
+            DenseGrid::new(4, 5).with_topology(Topology::KleinBottle { horizontal_twist })
+        };
+
+        let mut g = klein(true);
+        g.set(RowCol { row: 3, col: 1 }, true);
+        // Above (0, 3) is (3, 1).
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 3 }), 1);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 1 }), 0);
+
+        let mut g = klein(false);
+        g.set(RowCol { row: 1, col: 4 }, true);
+        // Left of (2, 0) is (1, 4).
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 0 }), 1);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 0);
+
+        let mut g = DenseGrid::new(3, 3).with_topology(Topology::Sphere);
+        g.set(RowCol { row: 0, col: 1 }, true);
+        // The top edge is joined to the left edge.
+        assert_eq!(g.count_live_neighbours(RowCol { row: 1, col: 0 }), 2);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 0 }), 1);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 2 }), 0);
+    }
+
+    #[test]
+    fn test_grow() {
+        let mut g = DenseGrid::new(4, 4);
+        g.set(RowCol { row: 1, col: 1 }, true);
+        g.set(RowCol { row: -10, col: 25 }, true);
+
+        assert!(g.at(RowCol { row: 1, col: 1 }));
+        assert!(g.at(RowCol { row: -10, col: 25 }));
+        assert_eq!(g.count_live_cells(), 2);
+        assert_eq!(g.count_live_neighbours(RowCol { row: -11, col: 24 }), 1);
+
+        let (rows, cols) = g.active_area();
+        assert!(rows.start <= -12 && rows.end > 3);
+        assert!(cols.start <= -1 && cols.end > 27);
+
+        let mut g = g.with_topology(Topology::Plane);
+        g.set(RowCol { row: 100, col: 100 }, true);
+        assert!(!g.at(RowCol { row: 100, col: 100 }));
+    }
+
+    #[test]
+    fn test_live_cells() {
+        let cells = [(1, 1), (-10, 25), (3, -7), (3, 2)].map(|(row, col)| RowCol { row, col });
+        let mut dense = DenseGrid::new(4, 4);
+        let mut sparse = SparseGrid::new(4, 4);
+        assert_eq!(dense.bounding_box(), None);
+
+        for &rc in &cells {
+            dense.set(rc, true);
+            sparse.set(rc, true);
+        }
+
+        let mut live: Vec<_> = dense.live_cells().collect();
+        live.sort_by_key(|rc| (rc.row, rc.col));
+        assert_eq!(live, [cells[1], cells[0], cells[2], cells[3]]);
+
+        assert_eq!(dense.bounding_box(), Some((-10..4, -7..26)));
+        assert_eq!(dense.bounding_box(), sparse.bounding_box());
+    }
+
+    #[test]
+    fn test_glider_leaves_grid() {
+        let mut g = DenseGrid::new(5, 5);
+        for &(row, col) in &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            g.set(RowCol { row, col }, true);
+        }
+
+        let mut u = Universe::new(g, Rule::new(vec![3], vec![2, 3]));
+        for _ in 0..100 {
+            u.step();
+            assert_eq!(u.live_cells, 5);
+        }
+
+        // A glider moves one cell diagonally every 4 generations.
+        assert!(u.at(25, 26));
+        assert!(u.at(27, 27));
+    }
+
+    #[test]
+    fn test_sleeping_tiles() {
+        let mut g = DenseGrid::new(100, 100);
+        // A block and a blinker, far apart.
+        for &(row, col) in &[(5, 5), (5, 6), (6, 5), (6, 6), (80, 80), (80, 81), (80, 82)] {
+            g.set(RowCol { row, col }, true);
+        }
+
+        let mut u = Universe::new(g, Rule::new(vec![3], vec![2, 3]));
+        for _ in 0..10 {
+            u.step();
+            assert_eq!(u.live_cells, 7);
+        }
+
+        // Only the blinker's tile changes.
+        assert_eq!(u.grid.changed.iter().filter(|&&c| c).count(), 1);
+        assert!(u.at(80, 80) && u.at(80, 81) && u.at(80, 82));
+        assert!(u.at(5, 5) && u.at(6, 6));
+    }
+
+    #[test]
+    fn test_next_generation() {
+        let dense: DenseGrid = random_grid(3, 50, 40);
+        let sparse: SparseGrid = random_grid(3, 50, 40);
+
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let mut dense = Universe::new(dense, rule.clone());
+        let mut sparse = Universe::new(sparse, rule);
+        for generation in 0..60 {
+            if generation % 2 == 0 {
+                dense.step();
+            } else {
+                dense.par_step(3);
+            }
+            sparse.step();
+
+            assert_eq!(dense.live_cells, sparse.live_cells);
+        }
+        for row in -50..100 {
+            for col in -50..90 {
+                assert_eq!(dense.at(row, col), sparse.at(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_larger_than_life() {
+        let mut life = DenseGrid::new(20, 20);
+        // An R-pentomino, which grows beyond the initial storage.
+        for &(row, col) in &[(9, 10), (9, 11), (10, 9), (10, 10), (11, 10)] {
+            life.set(RowCol { row, col }, true);
+        }
+        let mut ltl = Universe::new(life.clone(), "R1,C0,M0,S2..3,B3..3,NM".parse().unwrap());
+        let mut life = Universe::new(life, Rule::new(vec![3], vec![2, 3]));
+        for _ in 0..100 {
+            life.step();
+            ltl.par_step(3);
+            assert_eq!(ltl.live_cells, life.live_cells);
+        }
+        for row in -60..80 {
+            for col in -60..80 {
+                assert_eq!(ltl.at(row, col), life.at(row, col));
+            }
+        }
+
+        // Counts on a torus, checked cell by cell.
+        let ltl = match "R2,C0,M1,S3..5,B2..3,NN".parse().unwrap() {
+            Rule::LargerThanLife(ltl) => ltl,
+            _ => unreachable!(),
+        };
+        let mut g = DenseGrid::new(7, 9).with_topology(Topology::Torus);
+        for &(row, col) in &[(0, 0), (0, 1), (6, 8), (3, 4), (4, 4), (2, 7)] {
+            g.set(RowCol { row, col }, true);
+        }
+        let mut u = Universe::new(g.clone(), Rule::LargerThanLife(ltl));
+        u.step();
+        for row in 0..7i64 {
+            for col in 0..9i64 {
+                let count = (-2..=2i64)
+                    .flat_map(|dr| (-2..=2i64).map(move |dc| (dr, dc)))
+                    .filter(|&(dr, dc)| dr.abs() + dc.abs() <= 2)
+                    .filter(|&(dr, dc)| {
+                        g.at(RowCol {
+                            row: (row + dr).rem_euclid(7),
+                            col: (col + dc).rem_euclid(9),
+                        })
+                    })
+                    .count() as u32;
+                let alive = g.at(RowCol { row, col });
+                assert_eq!(u.at(row, col), ltl.lives(alive, count), "{} {}", row, col);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ltl_topologies() {
+        // Range 1 Larger than Life rules are life-like rules, whatever the topology.
+        for topology in [
+            Topology::KleinBottle {
+                horizontal_twist: true,
+            },
+            Topology::CrossSurface,
+            Topology::Sphere,
+        ] {
+            let grid = random_grid::<DenseGrid>(11, 12, 12).with_topology(topology);
+            let mut life = Universe::new(grid.clone(), Rule::new(vec![3], vec![2, 3]));
+            let mut ltl = Universe::new(grid, "R1,C0,M0,S2..3,B3..3,NM".parse().unwrap());
+            for _ in 0..20 {
+                life.step();
+                ltl.step();
+                assert_eq!(ltl.live_cells, life.live_cells, "{:?}", topology);
+            }
+            for row in 0..12 {
+                for col in 0..12 {
+                    assert_eq!(ltl.at(row, col), life.at(row, col), "{:?}", topology);
+                }
+            }
+        }
+
+        // Farther cells cross the borders as many times as needed.
+        let klein = Topology::KleinBottle {
+            horizontal_twist: true,
+        };
+        assert_eq!(klein.wrap(4, 5, -2, 1), Some((2, 3)));
+        assert_eq!(klein.wrap(4, 5, -6, 1), Some((2, 1)));
+        assert_eq!(klein.wrap(4, 5, 9, 1), Some((1, 1)));
+        assert_eq!(Topology::Sphere.wrap(4, 4, -3, 1), Some((1, 2)));
+        assert_eq!(Topology::Sphere.wrap(4, 4, 1, 6), Some((1, 1)));
+        assert_eq!(Topology::Sphere.wrap(4, 4, -1, -1), None);
+    }
+
+    #[test]
+    fn test_hexagonal() {
+        let mut dense = DenseGrid::new(30, 30);
+        let mut sparse = SparseGrid::new(30, 30);
+        for RowCol { row, col } in random_grid::<SparseGrid>(7, 10, 10).live_cells() {
+            dense.set(
+                RowCol {
+                    row: row + 10,
+                    col: col + 10,
+                },
+                true,
+            );
+            sparse.set(
+                RowCol {
+                    row: row + 10,
+                    col: col + 10,
+                },
+                true,
+            );
+        }
+
+        // The same rule, on range 1 neighbourhoods and with a Larger than Life engine.
+        let rule: Rule = "B2/S34H".parse().unwrap();
+        let mut ltl = Universe::new(dense.clone(), "R1,C0,M0,S3..4,B2..2,NH".parse().unwrap());
+        let mut dense = Universe::new(dense, rule.clone());
+        let mut sparse = Universe::new(sparse, rule);
+        for _ in 0..30 {
+            dense.step();
+            sparse.step();
+            ltl.step();
+            assert_eq!(dense.live_cells, sparse.live_cells);
+            assert_eq!(ltl.live_cells, sparse.live_cells);
+        }
+        for row in -30..60 {
+            for col in -30..60 {
+                assert_eq!(dense.at(row, col), sparse.at(row, col));
+                assert_eq!(ltl.at(row, col), sparse.at(row, col));
+            }
+        }
+    }
+
     #[test]
     fn test_new_from_rle() {
         // 3o$2bo$bo!
@@ -192,22 +1198,23 @@ mod test {
                 RleEntry::Dead(1),
                 RleEntry::Live(1),
             ],
+            position: None,
         };
 
         let dimension = rle.dimension();
         let g = DenseGrid::new_from_rle(&rle, dimension.0, dimension.1);
 
-        assert_eq!(g.at(RowCol { row: 0, col: 0 }), true);
-        assert_eq!(g.at(RowCol { row: 0, col: 1 }), true);
-        assert_eq!(g.at(RowCol { row: 0, col: 2 }), true);
+        assert!(g.at(RowCol { row: 0, col: 0 }));
+        assert!(g.at(RowCol { row: 0, col: 1 }));
+        assert!(g.at(RowCol { row: 0, col: 2 }));
 
-        assert_eq!(g.at(RowCol { row: 1, col: 0 }), false);
-        assert_eq!(g.at(RowCol { row: 1, col: 1 }), false);
-        assert_eq!(g.at(RowCol { row: 1, col: 2 }), true);
+        assert!(!g.at(RowCol { row: 1, col: 0 }));
+        assert!(!g.at(RowCol { row: 1, col: 1 }));
+        assert!(g.at(RowCol { row: 1, col: 2 }));
 
-        assert_eq!(g.at(RowCol { row: 2, col: 0 }), false);
-        assert_eq!(g.at(RowCol { row: 2, col: 1 }), true);
-        assert_eq!(g.at(RowCol { row: 2, col: 2 }), false);
+        assert!(!g.at(RowCol { row: 2, col: 0 }));
+        assert!(g.at(RowCol { row: 2, col: 1 }));
+        assert!(!g.at(RowCol { row: 2, col: 2 }));
     }
 } // mod test
 
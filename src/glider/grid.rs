@@ -1,22 +1,326 @@
 /* --------------------------------------------------------------------------------------------- */
 
-#[derive(Clone, Debug)]
+use std::ops::Range;
+
+use super::rle::{Rle, RleEntry};
+use super::rule::Rule;
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Position of a cell. The origin is the top-left cell of a bounded grid, or of the initial view
+/// of an unbounded one. Rows grow downwards and columns to the right.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct RowCol {
-    pub row: usize,
-    pub col: usize,
+    pub row: i64,
+    pub col: i64,
 }
 
+/// Offsets of the 8 neighbours of a cell, in row-major order. Bit i of a neighbourhood tells
+/// whether neighbour i is alive.
+pub const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
 /* --------------------------------------------------------------------------------------------- */
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Topology {
+    /// Cells beyond the borders are always dead.
+    Plane,
+    /// Opposite borders are joined.
+    Torus,
+    /// Opposite borders are joined, with a twist for the top and bottom ones if
+    /// `horizontal_twist`, for the left and right ones otherwise.
+    KleinBottle { horizontal_twist: bool },
+    /// Opposite borders are joined with a twist.
+    CrossSurface,
+    /// The top border is joined to the left one, the bottom border to the right one. The grid
+    /// must be square.
+    Sphere,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Topology {
+    /// Where a cell beyond the borders actually is, if anywhere. On a sphere, cells more than
+    /// `nb_rows` away, or beyond two borders, are nowhere.
+    pub fn wrap(
+        &self,
+        nb_rows: usize,
+        nb_cols: usize,
+        row: i64,
+        col: i64,
+    ) -> Option<(usize, usize)> {
+        let (h, w) = (nb_rows as i64, nb_cols as i64);
+        let inside = |r: i64, c: i64| (0..h).contains(&r) && (0..w).contains(&c);
+
+        let (flip_cols, flip_rows) = match *self {
+            Topology::Plane => return inside(row, col).then_some((row as usize, col as usize)),
+            Topology::Sphere => {
+                let (row, col) = match (row, col) {
+                    (r, c) if r < 0 && c >= 0 => (c, -r - 1),
+                    (r, c) if c < 0 && r >= 0 => (-c - 1, r),
+                    (r, c) if r >= h && c < w => (c, w - 1 - (r - h)),
+                    (r, c) if c >= w && r < h => (h - 1 - (c - w), r),
+                    rc => rc,
+                };
+                return inside(row, col).then_some((row as usize, col as usize));
+            }
+            Topology::Torus => (false, false),
+            Topology::KleinBottle { horizontal_twist } => (horizontal_twist, !horizontal_twist),
+            Topology::CrossSurface => (true, true),
+        };
+
+        // Twists cancel out when crossing the borders an even number of times.
+        let (mut row, mut col) = (row, col);
+        if !(0..h).contains(&row) {
+            if flip_cols && row.div_euclid(h) % 2 != 0 {
+                col = w - 1 - col;
+            }
+            row = row.rem_euclid(h);
+        }
+        if !(0..w).contains(&col) {
+            if flip_rows && col.div_euclid(w) % 2 != 0 {
+                row = h - 1 - row;
+            }
+            col = col.rem_euclid(w);
+        }
+
+        Some((row as usize, col as usize))
+    }
+}
+
+/// A grid with a fixed size and topology, as given by the suffix of a rule (e.g. `B3/S23:T64,64`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BoundedGrid {
+    pub topology: Topology,
+    pub nb_rows: usize,
+    pub nb_cols: usize,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Bounded grids are made of `nb_rows` rows and `nb_columns` columns from the origin, cells outside
+/// are always dead and can't be set. For unbounded grids, these dimensions are only the initial
+/// view, and a pattern bigger than them is kept whole.
 pub trait Grid {
     fn new(nb_rows: usize, nb_cols: usize) -> Self;
 
     fn at(&self, rc: RowCol) -> bool;
     fn set(&mut self, rc: RowCol, value: bool);
     fn count_live_neighbours(&self, rc: RowCol) -> u8;
+
+    /// 0 for a dead cell, 1 for a live one, and from 2 on for the dying states of Generations
+    /// rules, in which dying cells are neither alive nor able to come to life. Defaults to `at`,
+    /// for grids which only store live and dead cells.
+    fn state(&self, rc: RowCol) -> u8 {
+        self.at(rc) as u8
+    }
+
+    /// Defaults to `set`, in which case dying cells are lost.
+    fn set_state(&mut self, rc: RowCol, state: u8) {
+        self.set(rc, state == 1);
+    }
+
+    /// Live neighbours of a cell as a bit set, following `NEIGHBOURS`. Defaults to looking them up
+    /// with `at`.
+    fn neighbourhood(&self, rc: RowCol) -> u8 {
+        NEIGHBOURS
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, &(dr, dc))| {
+                let alive = self.at(RowCol {
+                    row: rc.row + dr,
+                    col: rc.col + dc,
+                });
+                acc | (alive as u8) << i
+            })
+    }
+
+    /// States of the neighbours of a cell, following `NEIGHBOURS`. Defaults to looking them up
+    /// with `state`.
+    fn neighbour_states(&self, rc: RowCol) -> [u8; 8] {
+        NEIGHBOURS.map(|(dr, dc)| {
+            self.state(RowCol {
+                row: rc.row + dr,
+                col: rc.col + dc,
+            })
+        })
+    }
+
     fn count_live_cells(&self) -> u64;
     fn nb_rows(&self) -> usize;
     fn nb_columns(&self) -> usize;
+
+    /// How the borders of bounded grids are joined, or None for unbounded grids.
+    fn topology(&self) -> Option<Topology> {
+        None
+    }
+
+    /// Whether the grid is limited to `nb_rows` and `nb_columns`, cells outside being always dead.
+    fn is_bounded(&self) -> bool {
+        self.topology().is_some()
+    }
+
+    /// An empty grid with the same dimensions and settings.
+    fn empty_like(&self) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(self.nb_rows(), self.nb_columns())
+    }
+
+    /// Rows and columns holding all cells which might be alive at the next generation. Defaults
+    /// to the whole grid.
+    fn active_area(&self) -> (Range<i64>, Range<i64>) {
+        (0..self.nb_rows() as i64, 0..self.nb_columns() as i64)
+    }
+
+    /// Cells which might be alive at the next generation. Defaults to the active area.
+    fn active_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        self.active_cells_in(self.active_area().0)
+    }
+
+    /// Same as `active_cells`, among the cells of `rows`. Defaults to the cells of these rows in
+    /// the active area.
+    fn active_cells_in(&self, rows: Range<i64>) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        let (_, cols) = self.active_area();
+
+        Box::new(rows.flat_map(move |row| cols.clone().map(move |col| RowCol { row, col })))
+    }
+
+    /// Live cells, in no particular order. Defaults to the live cells of the active area.
+    fn live_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        Box::new(self.active_cells().filter(move |&rc| self.at(rc)))
+    }
+
+    /// Live and dying cells with their state, in no particular order. Defaults to live cells.
+    fn cell_states(&self) -> Box<dyn Iterator<Item = (RowCol, u8)> + '_> {
+        Box::new(self.live_cells().map(|rc| (rc, 1)))
+    }
+
+    /// Smallest rows and columns holding all live cells, or None if there are none.
+    fn bounding_box(&self) -> Option<(Range<i64>, Range<i64>)> {
+        self.live_cells().fold(None, |bounds, rc| {
+            Some(match bounds {
+                None => (rc.row..rc.row + 1, rc.col..rc.col + 1),
+                Some((rows, cols)) => (
+                    i64::min(rows.start, rc.row)..i64::max(rows.end, rc.row + 1),
+                    i64::min(cols.start, rc.col)..i64::max(cols.end, rc.col + 1),
+                ),
+            })
+        })
+    }
+
+    /// Turns this grid into an empty grid like `other`, reusing its memory when possible, to
+    /// receive the next generation of `other`.
+    fn clear_like(&mut self, other: &Self)
+    where
+        Self: Sized,
+    {
+        *self = other.empty_like();
+    }
+
+    /// Why generations of `rule` can't be computed on this grid, if they can't. Defaults to rules
+    /// which `Universe::step` computes cell by cell.
+    fn check_rule(&self, rule: &Rule) -> Result<(), &'static str> {
+        match rule {
+            Rule::LargerThanLife(_) => Err("Larger than Life rules need a dense grid"),
+            Rule::Lenia(_) => Err("Lenia rules need a Lenia grid"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Computes the next generation into `next`, an empty grid like this one, for grids which can
+    /// do it faster than `Universe::step` does cell by cell, possibly with several threads. Returns
+    /// false if the grid doesn't know how to.
+    fn next_generation(&self, _rule: &Rule, _next: &mut Self, _nb_threads: usize) -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
+    fn new_from_rle(rle: &Rle, rows: usize, columns: usize) -> Self
+    where
+        Self: Sized,
+    {
+        let (rle_rows, rle_cols) = rle.dimension();
+        let rows = usize::max(rows, rle_rows);
+        let columns = usize::max(columns, rle_cols);
+
+        let mut grid = Self::new(rows, columns);
+
+        // As Golly does, a pattern without a position is centered.
+        let (row_shift, col_shift) = match rle.position {
+            Some(RowCol { row, col }) => (row, col),
+            None => (-(rle_rows as i64 / 2), -(rle_cols as i64 / 2)),
+        };
+        let row_shift = (rows / 2) as i64 + row_shift;
+        let col_shift = (columns / 2) as i64 + col_shift;
+
+        let mut row = row_shift;
+        let mut col = col_shift;
+
+        for entry in &rle.pattern {
+            match entry {
+                RleEntry::Live(nb) => {
+                    for col in col..col + *nb as i64 {
+                        grid.set(RowCol { row, col }, true);
+                    }
+                    col += *nb as i64;
+                }
+                RleEntry::Dead(nb) => {
+                    col += *nb as i64;
+                }
+                RleEntry::State(nb, state) => {
+                    for col in col..col + *nb as i64 {
+                        grid.set_state(RowCol { row, col }, *state);
+                    }
+                    col += *nb as i64;
+                }
+                RleEntry::NewRow(nb) => {
+                    row += *nb as i64;
+                    col = col_shift;
+                }
+            };
+        }
+
+        grid
+    }
 }
 
 /* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// A grid where about a quarter of the cells are alive, drawn from `seed` by a linear
+    /// congruential generator.
+    pub fn random_grid<G: Grid>(seed: u64, nb_rows: usize, nb_cols: usize) -> G {
+        let mut grid = G::new(nb_rows, nb_cols);
+        let mut seed = seed;
+
+        for row in 0..nb_rows as i64 {
+            for col in 0..nb_cols as i64 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                if seed >> 62 == 0 {
+                    grid.set(RowCol { row, col }, true);
+                }
+            }
+        }
+
+        grid
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
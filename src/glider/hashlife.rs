@@ -0,0 +1,644 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use super::grid::{Grid, RowCol, NEIGHBOURS};
+use super::rule::{LifeLike, Rule};
+
+/* --------------------------------------------------------------------------------------------- */
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+// Past this number of nodes, a grid is copied into a fresh store holding only the nodes it uses.
+const MAX_NODES: usize = 1 << 22;
+
+/// Largest k of 2^k generations computed at once, so that the root, at least 3 levels above k,
+/// keeps its coordinates within an `i64`.
+pub const MAX_STEP: u8 = 56;
+
+/* --------------------------------------------------------------------------------------------- */
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    population: u64,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Hash-consed quadtree nodes, shared by all generations of a universe.
+#[derive(Debug)]
+struct Store {
+    nodes: Vec<Node>,
+    index: HashMap<[NodeId; 4], NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    rule: Option<LifeLike>,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Store {
+    fn new() -> Self {
+        let leaf = |population| Node {
+            level: 0,
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            population,
+        };
+
+        Store {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            rule: None,
+        }
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    fn population(&self, id: NodeId) -> u64 {
+        self.nodes[id as usize].population
+    }
+
+    fn set_rule(&mut self, rule: &LifeLike) {
+        if self.rule.as_ref() != Some(rule) {
+            self.results.clear();
+            self.rule = Some(*rule);
+        }
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        if let Some(&id) = self.index.get(&[nw, ne, sw, se]) {
+            return id;
+        }
+
+        let node = Node {
+            level: self.level(nw) + 1,
+            nw,
+            ne,
+            sw,
+            se,
+            population: self.population(nw)
+                + self.population(ne)
+                + self.population(sw)
+                + self.population(se),
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.index.insert([nw, ne, sw, se], id);
+
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let id = self.join(e, e, e, e);
+            self.empty.push(id);
+        }
+
+        self.empty[level as usize]
+    }
+
+    // Doubles the size of a node, keeping it at the centre.
+    fn expand(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let e = self.empty(n.level - 1);
+
+        let nw = self.join(e, e, e, n.nw);
+        let ne = self.join(e, e, n.ne, e);
+        let sw = self.join(e, n.sw, e, e);
+        let se = self.join(n.se, e, e, e);
+
+        self.join(nw, ne, sw, se)
+    }
+
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let (nw, ne, sw, se) = (
+            self.node(n.nw),
+            self.node(n.ne),
+            self.node(n.sw),
+            self.node(n.se),
+        );
+
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    fn horizontal_centre(&mut self, w: NodeId, e: NodeId) -> NodeId {
+        let (w, e) = (self.node(w), self.node(e));
+
+        self.join(w.ne, e.nw, w.se, e.sw)
+    }
+
+    fn vertical_centre(&mut self, n: NodeId, s: NodeId) -> NodeId {
+        let (n, s) = (self.node(n), self.node(s));
+
+        self.join(n.sw, n.se, s.nw, s.ne)
+    }
+
+    // The centre of a level 2 node, one generation later.
+    fn base_case(&mut self, id: NodeId) -> NodeId {
+        let rule = self.rule.expect("Rule not set");
+        let mut cells = [[false; 4]; 4];
+
+        let n = self.node(id);
+        for (quadrant, (row, col)) in [
+            (n.nw, (0, 0)),
+            (n.ne, (0, 2)),
+            (n.sw, (2, 0)),
+            (n.se, (2, 2)),
+        ]
+        .iter()
+        .copied()
+        {
+            let q = self.node(quadrant);
+            cells[row][col] = q.nw == ALIVE;
+            cells[row][col + 1] = q.ne == ALIVE;
+            cells[row + 1][col] = q.sw == ALIVE;
+            cells[row + 1][col + 1] = q.se == ALIVE;
+        }
+
+        let next = |row: usize, col: usize| -> NodeId {
+            let neighbourhood = NEIGHBOURS
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, &(dr, dc))| {
+                    let alive = cells[(row as i64 + dr) as usize][(col as i64 + dc) as usize];
+                    acc | (alive as u8) << i
+                });
+
+            rule.lives_in(cells[row][col], neighbourhood) as NodeId
+        };
+
+        self.join(next(1, 1), next(1, 2), next(2, 1), next(2, 2))
+    }
+
+    // The centre of a node of level n >= 2, 2^j generations later, with j <= n - 2.
+    fn next(&mut self, id: NodeId, j: u8) -> NodeId {
+        let level = self.level(id);
+        debug_assert!(level >= 2 && j <= level - 2);
+
+        if self.population(id) == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.base_case(id)
+        } else {
+            let n = self.node(id);
+
+            let n00 = n.nw;
+            let n01 = self.horizontal_centre(n.nw, n.ne);
+            let n02 = n.ne;
+            let n10 = self.vertical_centre(n.nw, n.sw);
+            let n11 = self.centre(id);
+            let n12 = self.vertical_centre(n.ne, n.se);
+            let n20 = n.sw;
+            let n21 = self.horizontal_centre(n.sw, n.se);
+            let n22 = n.se;
+
+            let full_speed = j == level - 2;
+            let phase_one = |store: &mut Self, id| {
+                if full_speed {
+                    store.next(id, j - 1)
+                } else {
+                    store.centre(id)
+                }
+            };
+
+            let r00 = phase_one(self, n00);
+            let r01 = phase_one(self, n01);
+            let r02 = phase_one(self, n02);
+            let r10 = phase_one(self, n10);
+            let r11 = phase_one(self, n11);
+            let r12 = phase_one(self, n12);
+            let r20 = phase_one(self, n20);
+            let r21 = phase_one(self, n21);
+            let r22 = phase_one(self, n22);
+
+            let phase_two_j = if full_speed { j - 1 } else { j };
+
+            let q = self.join(r00, r01, r10, r11);
+            let nw = self.next(q, phase_two_j);
+            let q = self.join(r01, r02, r11, r12);
+            let ne = self.next(q, phase_two_j);
+            let q = self.join(r10, r11, r20, r21);
+            let sw = self.next(q, phase_two_j);
+            let q = self.join(r11, r12, r21, r22);
+            let se = self.next(q, phase_two_j);
+
+            self.join(nw, ne, sw, se)
+        };
+
+        self.results.insert((id, j), result);
+
+        result
+    }
+
+    fn cell(&self, id: NodeId, row: u64, col: u64) -> bool {
+        let mut id = id;
+        let mut row = row;
+        let mut col = col;
+
+        loop {
+            let n = self.node(id);
+            if n.level == 0 {
+                return id == ALIVE;
+            }
+            if n.population == 0 {
+                return false;
+            }
+
+            let half = 1u64 << (n.level - 1);
+            id = match (row < half, col < half) {
+                (true, true) => n.nw,
+                (true, false) => n.ne,
+                (false, true) => n.sw,
+                (false, false) => n.se,
+            };
+            row %= half;
+            col %= half;
+        }
+    }
+
+    fn set_cell(&mut self, id: NodeId, row: u64, col: u64, value: bool) -> NodeId {
+        let n = self.node(id);
+        if n.level == 0 {
+            return value as NodeId;
+        }
+
+        let half = 1u64 << (n.level - 1);
+        let (r, c) = (row % half, col % half);
+
+        match (row < half, col < half) {
+            (true, true) => {
+                let nw = self.set_cell(n.nw, r, c, value);
+                self.join(nw, n.ne, n.sw, n.se)
+            }
+            (true, false) => {
+                let ne = self.set_cell(n.ne, r, c, value);
+                self.join(n.nw, ne, n.sw, n.se)
+            }
+            (false, true) => {
+                let sw = self.set_cell(n.sw, r, c, value);
+                self.join(n.nw, n.ne, sw, n.se)
+            }
+            (false, false) => {
+                let se = self.set_cell(n.se, r, c, value);
+                self.join(n.nw, n.ne, n.sw, se)
+            }
+        }
+    }
+
+    fn live_cells(&self, id: NodeId, row: i64, col: i64, cells: &mut Vec<(i64, i64)>) {
+        let n = self.node(id);
+        if n.population == 0 {
+            return;
+        }
+        if n.level == 0 {
+            cells.push((row, col));
+            return;
+        }
+
+        let half = 1i64 << (n.level - 1);
+        self.live_cells(n.nw, row, col, cells);
+        self.live_cells(n.ne, row, col + half, cells);
+        self.live_cells(n.sw, row + half, col, cells);
+        self.live_cells(n.se, row + half, col + half, cells);
+    }
+
+    // Copies a node and its descendants from another store.
+    fn import(
+        &mut self,
+        other: &Store,
+        id: NodeId,
+        copied: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if id == DEAD || id == ALIVE {
+            return id;
+        }
+        if let Some(&new_id) = copied.get(&id) {
+            return new_id;
+        }
+
+        let n = other.node(id);
+        let nw = self.import(other, n.nw, copied);
+        let ne = self.import(other, n.ne, copied);
+        let sw = self.import(other, n.sw, copied);
+        let se = self.import(other, n.se, copied);
+        let new_id = self.join(nw, ne, sw, se);
+        copied.insert(id, new_id);
+
+        new_id
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// A quadtree of hash-consed nodes, with memoized future of each node, which makes it possible to
+/// compute 2^k generations at once. The tree grows as needed, so a pattern is not limited by the
+/// number of rows and columns, which only describe the visible part of the universe.
+#[derive(Clone, Debug)]
+pub struct HashLife {
+    store: Rc<RefCell<Store>>,
+    root: NodeId,
+    // Position of the root's top-left corner.
+    origin: (i64, i64),
+    nb_rows: usize,
+    nb_cols: usize,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl HashLife {
+    /// Computes the grid 2^k generations later.
+    pub fn step(&self, rule: &LifeLike, k: u8) -> Self {
+        assert!(k <= MAX_STEP, "Can't step 2^{} generations at once", k);
+        let mut next = self.clone();
+
+        {
+            let mut store = self.store.borrow_mut();
+            store.set_rule(rule);
+
+            // Make sure that the pattern can't escape the centre of the root during 2^k generations.
+            loop {
+                let level = store.level(next.root);
+                let inner = store.centre(next.root);
+                let inner = store.centre(inner);
+                if level >= k + 3 && store.population(inner) == store.population(next.root) {
+                    break;
+                }
+                next.expand(&mut store);
+            }
+
+            let level = store.level(next.root);
+            let quarter = 1i64 << (level - 2);
+
+            next.root = store.next(next.root, k);
+            next.origin = (next.origin.0 + quarter, next.origin.1 + quarter);
+        }
+
+        if self.store.borrow().nodes.len() > MAX_NODES {
+            next.compact();
+        }
+
+        next
+    }
+
+    fn expand(&mut self, store: &mut Store) {
+        let quarter = 1i64 << (store.level(self.root) - 1);
+
+        self.root = store.expand(self.root);
+        self.origin = (self.origin.0 - quarter, self.origin.1 - quarter);
+    }
+
+    fn compact(&mut self) {
+        let mut store = Store::new();
+        self.root = store.import(&self.store.borrow(), self.root, &mut HashMap::new());
+        self.store = Rc::new(RefCell::new(store));
+    }
+
+    // Position of a cell relatively to the root, if it's in the tree.
+    fn relative(&self, rc: RowCol, store: &Store) -> Option<(u64, u64)> {
+        let size = 1i64 << store.level(self.root);
+        let row = rc.row - self.origin.0;
+        let col = rc.col - self.origin.1;
+
+        if (0..size).contains(&row) && (0..size).contains(&col) {
+            Some((row as u64, col as u64))
+        } else {
+            None
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Grid for HashLife {
+    fn new(nb_rows: usize, nb_cols: usize) -> Self {
+        let mut level = 3;
+        while (1usize << level) < usize::max(nb_rows, nb_cols) {
+            level += 1;
+        }
+
+        let mut store = Store::new();
+        let root = store.empty(level);
+
+        HashLife {
+            store: Rc::new(RefCell::new(store)),
+            root,
+            origin: (0, 0),
+            nb_rows,
+            nb_cols,
+        }
+    }
+
+    fn at(&self, rc: RowCol) -> bool {
+        let store = self.store.borrow();
+
+        match self.relative(rc, &store) {
+            Some((row, col)) => store.cell(self.root, row, col),
+            None => false,
+        }
+    }
+
+    fn set(&mut self, rc: RowCol, value: bool) {
+        let store = Rc::clone(&self.store);
+        let mut store = store.borrow_mut();
+
+        loop {
+            if let Some((row, col)) = self.relative(rc, &store) {
+                self.root = store.set_cell(self.root, row, col, value);
+                return;
+            }
+            if !value {
+                return;
+            }
+            self.expand(&mut store);
+        }
+    }
+
+    fn count_live_neighbours(&self, rc: RowCol) -> u8 {
+        let mut count = 0;
+
+        for row in rc.row - 1..=rc.row + 1 {
+            for col in rc.col - 1..=rc.col + 1 {
+                if (row, col) != (rc.row, rc.col) && self.at(RowCol { row, col }) {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+
+    fn count_live_cells(&self) -> u64 {
+        self.store.borrow().population(self.root)
+    }
+
+    fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    fn nb_columns(&self) -> usize {
+        self.nb_cols
+    }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        let mut cells = vec![];
+        self.store
+            .borrow()
+            .live_cells(self.root, self.origin.0, self.origin.1, &mut cells);
+
+        Box::new(cells.into_iter().map(|(row, col)| RowCol { row, col }))
+    }
+
+    // Jumps only compute life-like rules with 2 states, and B0 rules can't be emulated on the
+    // complement of the quadtree.
+    fn check_rule(&self, rule: &Rule) -> Result<(), &'static str> {
+        match rule {
+            Rule::LifeLike(_) if rule.nb_states() == 2 && !rule.is_inverted(1) => Ok(()),
+            _ => Err("HashLife only computes life-like rules with 2 states and without B0"),
+        }
+    }
+
+    fn active_area(&self) -> (Range<i64>, Range<i64>) {
+        match self.bounding_box() {
+            Some((rows, cols)) => (rows.start - 1..rows.end + 1, cols.start - 1..cols.end + 1),
+            None => (0..0, 0..0),
+        }
+    }
+
+    fn active_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        let mut active = vec![];
+
+        for RowCol { row, col } in self.live_cells() {
+            for r in row - 1..=row + 1 {
+                for c in col - 1..=col + 1 {
+                    active.push(RowCol { row: r, col: c });
+                }
+            }
+        }
+        active.sort_by_key(|rc| (rc.row, rc.col));
+        active.dedup();
+
+        Box::new(active.into_iter())
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::glider::dense_grid::DenseGrid;
+    use crate::glider::universe::Universe;
+
+    #[test]
+    fn test_set_at() {
+        let mut g = HashLife::new(4, 4);
+        g.set(RowCol { row: 1, col: 2 }, true);
+        g.set(RowCol { row: 100, col: 3 }, true);
+        g.set(
+            RowCol {
+                row: -20,
+                col: -300,
+            },
+            true,
+        );
+
+        assert!(g.at(RowCol { row: 1, col: 2 }));
+        assert!(g.at(RowCol {
+            row: -20,
+            col: -300
+        }));
+        assert!(g.at(RowCol { row: 100, col: 3 }));
+        assert!(!g.at(RowCol { row: 2, col: 1 }));
+        assert_eq!(g.count_live_cells(), 3);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 2 }), 1);
+
+        g.set(RowCol { row: 1, col: 2 }, false);
+        assert_eq!(g.count_live_cells(), 2);
+    }
+
+    #[test]
+    fn test_step() {
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let mut dense = DenseGrid::new(64, 64);
+        let mut hashlife = HashLife::new(64, 64);
+
+        // An R-pentomino, which is still evolving after 64 generations.
+        for &(row, col) in &[(30, 31), (30, 32), (31, 30), (31, 31), (32, 31)] {
+            dense.set(RowCol { row, col }, true);
+            hashlife.set(RowCol { row, col }, true);
+        }
+
+        let mut dense = Universe::new(dense, rule.clone());
+        let mut hashlife = Universe::new(hashlife, rule);
+        for _ in 0..16 {
+            dense.step();
+        }
+        hashlife = hashlife.jump(2).jump(3).jump(0).jump(1).jump(0);
+
+        assert_eq!(hashlife.generation, 16);
+        assert_eq!(hashlife.live_cells, dense.live_cells);
+        for row in 0..64 {
+            for col in 0..64 {
+                assert_eq!(hashlife.at(row, col), dense.at(row, col));
+            }
+        }
+
+        let jumped = hashlife.jump(0);
+        hashlife.step();
+        assert_eq!(hashlife.live_cells, jumped.live_cells);
+    }
+
+    #[test]
+    fn test_big_jump() {
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+        let mut g = HashLife::new(8, 8);
+        for &(row, col) in &glider {
+            g.set(RowCol { row, col }, true);
+        }
+
+        // A glider moves one cell diagonally every 4 generations.
+        let u = Universe::new(g, rule).jump(10).jump(20);
+        let shift = (1 << 8) + (1 << 18);
+
+        assert_eq!(u.generation, (1 << 10) + (1 << 20));
+        assert_eq!(u.live_cells, 5);
+        for &(row, col) in &glider {
+            assert!(u.at(row + shift, col + shift));
+        }
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
@@ -0,0 +1,277 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use super::grid::{Grid, RowCol, Topology, NEIGHBOURS};
+use super::rule::{Lenia, Rule};
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Cells of Lenia rules, which take values from 0 to 1 rather than being dead or alive, on a
+/// torus. Non-zero cells are alive, and their state goes from 1 to 254 as their value grows.
+#[derive(Clone, Debug)]
+pub struct LeniaGrid {
+    cells: Vec<f32>,
+    nb_rows: usize,
+    nb_cols: usize,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl LeniaGrid {
+    fn index(&self, rc: RowCol) -> usize {
+        let row = rc.row.rem_euclid(self.nb_rows as i64) as usize;
+        let col = rc.col.rem_euclid(self.nb_cols as i64) as usize;
+
+        row * self.nb_cols + col
+    }
+
+    /// Value of a cell, from 0 to 1.
+    pub fn value(&self, rc: RowCol) -> f32 {
+        self.cells[self.index(rc)]
+    }
+
+    pub fn set_value(&mut self, rc: RowCol, value: f32) {
+        let index = self.index(rc);
+        self.cells[index] = value.clamp(0.0, 1.0);
+    }
+
+    // Rows, columns and weights of the cells around a cell, weights adding up to 1.
+    fn kernel(lenia: &Lenia) -> Vec<(i64, i64, f32)> {
+        let radius = lenia.radius as i64;
+        let mut kernel = vec![];
+
+        for dr in -radius..=radius {
+            for dc in -radius..=radius {
+                let weight = lenia.kernel(((dr * dr + dc * dc) as f32).sqrt());
+                if weight > 0.0 {
+                    kernel.push((dr, dc, weight));
+                }
+            }
+        }
+
+        let total: f32 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+        for (_, _, weight) in &mut kernel {
+            *weight /= total;
+        }
+
+        kernel
+    }
+
+    // Wrapped indices from `-radius` to `len + radius`, times `stride`, so that the kernel around
+    // any cell is read without computing remainders.
+    fn wrapped(len: usize, radius: i64, stride: usize) -> Vec<usize> {
+        (-radius..len as i64 + radius)
+            .map(|i| i.rem_euclid(len as i64) as usize * stride)
+            .collect()
+    }
+
+    // Computes the next generation of the rows of a band starting at `first_row`, `rows` and
+    // `cols` being the wrapped indices of rows and columns.
+    fn next_band(
+        &self,
+        lenia: &Lenia,
+        kernel: &[(i64, i64, f32)],
+        (rows, cols): (&[usize], &[usize]),
+        first_row: usize,
+        band: &mut [f32],
+    ) {
+        let radius = lenia.radius as i64;
+
+        for (i, cell) in band.iter_mut().enumerate() {
+            let row = (first_row + i / self.nb_cols) as i64 + radius;
+            let col = (i % self.nb_cols) as i64 + radius;
+
+            let potential: f32 = kernel
+                .iter()
+                .map(|&(dr, dc, weight)| {
+                    weight * self.cells[rows[(row + dr) as usize] + cols[(col + dc) as usize]]
+                })
+                .sum();
+            let value = self.cells[first_row * self.nb_cols + i];
+
+            *cell = (value + lenia.growth(potential) / lenia.time as f32).clamp(0.0, 1.0);
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Grid for LeniaGrid {
+    fn new(nb_rows: usize, nb_cols: usize) -> Self {
+        LeniaGrid {
+            cells: vec![0.0; nb_rows * nb_cols],
+            nb_rows,
+            nb_cols,
+        }
+    }
+
+    fn at(&self, rc: RowCol) -> bool {
+        self.value(rc) > 0.0
+    }
+
+    fn set(&mut self, rc: RowCol, value: bool) {
+        self.set_value(rc, value as u8 as f32);
+    }
+
+    fn state(&self, rc: RowCol) -> u8 {
+        (self.value(rc) * 254.0).ceil() as u8
+    }
+
+    fn set_state(&mut self, rc: RowCol, state: u8) {
+        self.set_value(rc, state as f32 / 254.0);
+    }
+
+    fn count_live_neighbours(&self, rc: RowCol) -> u8 {
+        NEIGHBOURS
+            .iter()
+            .filter(|&&(dr, dc)| {
+                self.at(RowCol {
+                    row: rc.row + dr,
+                    col: rc.col + dc,
+                })
+            })
+            .count() as u8
+    }
+
+    fn count_live_cells(&self) -> u64 {
+        self.cells.iter().filter(|&&value| value > 0.0).count() as u64
+    }
+
+    fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    fn nb_columns(&self) -> usize {
+        self.nb_cols
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(Topology::Torus)
+    }
+
+    fn cell_states(&self) -> Box<dyn Iterator<Item = (RowCol, u8)> + '_> {
+        Box::new(self.live_cells().map(move |rc| (rc, self.state(rc))))
+    }
+
+    fn check_rule(&self, rule: &Rule) -> Result<(), &'static str> {
+        match rule {
+            Rule::Lenia(_) => Ok(()),
+            _ => Err("Lenia grids only compute Lenia rules"),
+        }
+    }
+
+    fn next_generation(&self, rule: &Rule, next: &mut Self, nb_threads: usize) -> bool {
+        let lenia = match rule {
+            Rule::Lenia(lenia) => lenia,
+            _ => return false,
+        };
+
+        let kernel = Self::kernel(lenia);
+        let radius = lenia.radius as i64;
+        let rows = Self::wrapped(self.nb_rows, radius, self.nb_cols);
+        let cols = Self::wrapped(self.nb_cols, radius, 1);
+        let band_rows = usize::max(1, self.nb_rows.div_ceil(usize::max(1, nb_threads)));
+
+        std::thread::scope(|scope| {
+            for (i, band) in next.cells.chunks_mut(band_rows * self.nb_cols).enumerate() {
+                let (kernel, wrapped) = (&kernel, (&rows[..], &cols[..]));
+                scope.spawn(move || self.next_band(lenia, kernel, wrapped, i * band_rows, band));
+            }
+        });
+
+        true
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_states() {
+        let mut grid = LeniaGrid::new(4, 4);
+        let rc = RowCol { row: 1, col: 2 };
+
+        grid.set_state(rc, 127);
+        assert_eq!(grid.value(rc), 0.5);
+        assert_eq!(grid.state(rc), 127);
+        // Rows and columns wrap around.
+        assert_eq!(grid.state(RowCol { row: 5, col: -2 }), 127);
+
+        grid.set_value(rc, 0.001);
+        assert_eq!(grid.state(rc), 1);
+        assert_eq!(grid.count_live_cells(), 1);
+        grid.set(rc, true);
+        assert_eq!(grid.value(rc), 1.0);
+    }
+
+    #[test]
+    fn test_next_generation() {
+        let lenia = Lenia {
+            radius: 3,
+            time: 10,
+            mu: 0.15,
+            sigma: 0.015,
+        };
+        let rule = Rule::Lenia(lenia);
+        let kernel = LeniaGrid::kernel(&lenia);
+        let total: f32 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+
+        // Cells around which the weighted sum is `mu` grow the most, on the whole torus.
+        let mut grid = LeniaGrid::new(5, 7);
+        for value in grid.cells.iter_mut() {
+            *value = 0.15;
+        }
+        let mut next = grid.empty_like();
+        assert!(grid.next_generation(&rule, &mut next, 2));
+        assert!(next.cells.iter().all(|&value| (value - 0.25).abs() < 1e-5));
+
+        // Empty cells stay empty.
+        let mut next = LeniaGrid::new(5, 7);
+        assert!(LeniaGrid::new(5, 7).next_generation(&rule, &mut next, 1));
+        assert_eq!(next.count_live_cells(), 0);
+
+        // Cells across the borders are as near as the others, with a growth depending on them.
+        let rule = Rule::Lenia(Lenia {
+            sigma: 0.3,
+            ..lenia
+        });
+        let mut corner = LeniaGrid::new(9, 11);
+        let mut centre = LeniaGrid::new(9, 11);
+        for (i, &(row, col)) in [(0, 0), (0, 1), (-1, 2), (1, -1), (-2, -2)]
+            .iter()
+            .enumerate()
+        {
+            let value = 0.2 * (i + 1) as f32;
+            corner.set_value(RowCol { row, col }, value);
+            centre.set_value(
+                RowCol {
+                    row: row + 4,
+                    col: col + 5,
+                },
+                value,
+            );
+        }
+        let mut next_corner = corner.empty_like();
+        let mut next_centre = centre.empty_like();
+        assert!(corner.next_generation(&rule, &mut next_corner, 3));
+        assert!(centre.next_generation(&rule, &mut next_centre, 1));
+        for row in 0..9 {
+            for col in 0..11 {
+                let rc = RowCol { row, col };
+                let shifted = RowCol {
+                    row: row + 4,
+                    col: col + 5,
+                };
+                assert!((next_corner.value(rc) - next_centre.value(shifted)).abs() < 1e-6);
+            }
+        }
+
+        assert!(!grid.next_generation(&"B3/S23".parse().unwrap(), &mut next, 1));
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
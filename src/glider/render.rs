@@ -6,7 +6,8 @@ use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 
-use super::grid::Grid;
+use super::grid::{Grid, RowCol};
+use super::rule::{Rule, Shape};
 use super::universe::Universe;
 
 /* --------------------------------------------------------------------------------------------- */
@@ -19,14 +20,54 @@ enum State {
 
 /* --------------------------------------------------------------------------------------------- */
 
-pub fn render_universe<G: Grid>(mut u: Universe<G>) {
+// Live cells are white, and dying cells fade from blue to black.
+fn state_color(state: u8, nb_states: u8) -> Color {
+    if state == 1 {
+        return Color::RGB(255, 255, 255);
+    }
+
+    let fade = |c: u32| (c * (nb_states - state) as u32 / (nb_states - 1) as u32) as u8;
+    Color::RGB(fade(64), fade(128), fade(255))
+}
+
+// Cells of Lenia rules go from dark blue to cyan, yellow and red as their value grows.
+fn value_color(state: u8) -> Color {
+    const STOPS: [(f32, f32, f32); 5] = [
+        (0.0, 0.0, 96.0),
+        (0.0, 128.0, 255.0),
+        (0.0, 255.0, 128.0),
+        (255.0, 255.0, 0.0),
+        (255.0, 0.0, 0.0),
+    ];
+
+    let position = state as f32 / 254.0 * (STOPS.len() - 1) as f32;
+    let i = usize::min(position as usize, STOPS.len() - 2);
+    let t = position - i as f32;
+    let mix = |a: f32, b: f32| (a + (b - a) * t) as u8;
+    let ((r0, g0, b0), (r1, g1, b1)) = (STOPS[i], STOPS[i + 1]);
+
+    Color::RGB(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+pub fn render_universe<G: Grid>(mut u: Universe<G>, mut step: impl FnMut(&mut Universe<G>)) {
     let window_rows = 1000;
     let window_cols = 1000;
 
-    let cell_size = 1u32;
+    // Lenia grids are small enough to be zoomed in.
+    let cell_size = match u.rule {
+        Rule::Lenia(_) => u32::max(
+            1,
+            window_rows / usize::max(u.grid.nb_rows(), u.grid.nb_columns()) as u32,
+        ),
+        _ => 1u32,
+    };
 
     let background_color = Color::RGB(0, 0, 0);
-    let cell_color = Color::RGB(255, 255, 255);
+    let birth_color = Color::RGB(0, 255, 0);
+    let death_color = Color::RGB(128, 0, 0);
+    let ant_color = Color::RGB(255, 0, 0);
     let font_color = Color::RGB(255, 255, 255);
 
     let sdl_context = sdl2::init().unwrap();
@@ -54,7 +95,11 @@ pub fn render_universe<G: Grid>(mut u: Universe<G>) {
     let mut event_pump = sdl_context.event_pump().unwrap();
 
     let mut state = State::Paused;
-    let mut first = true;
+    // Whether to draw the universe without stepping it.
+    let mut redraw = true;
+    let mut highlight = false;
+    // Top-left cell of the window.
+    let mut view = RowCol { row: 0, col: 0 };
 
     'running: loop {
         for event in event_pump.poll_iter() {
@@ -76,19 +121,61 @@ pub fn render_universe<G: Grid>(mut u: Universe<G>) {
                     }
                 }
 
+                Event::KeyDown {
+                    keycode: Some(Keycode::D),
+                    repeat: false,
+                    ..
+                } => highlight = !highlight,
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    repeat: false,
+                    ..
+                } => {
+                    // Centres live cells in the window.
+                    if let Some((rows, cols)) = u.grid.bounding_box() {
+                        view = RowCol {
+                            row: (rows.start + rows.end - u.grid.nb_rows() as i64) / 2,
+                            col: (cols.start + cols.end - u.grid.nb_columns() as i64) / 2,
+                        };
+                        redraw = true;
+                    }
+                }
+
                 _ => {}
             }
         }
 
-        if State::Running == state || first {
-            canvas.set_draw_color(background_color);
+        if State::Running == state || redraw {
+            // With B0 rules, live cells of the grid are dead ones at some generations.
+            let inverted = u.is_inverted();
+            let live_color = state_color(1, u.rule.nb_states());
+            canvas.set_draw_color(if inverted {
+                live_color
+            } else {
+                background_color
+            });
             canvas.clear();
 
-            canvas.set_draw_color(cell_color);
-
+            let population = match u.population() {
+                Some(population) => population.to_string(),
+                None => "inf".to_string(),
+            };
             let surface = font
-                .render(&format!("g:{} l:{}", u.generation, u.live_cells))
-                .blended(font_color)
+                .render(&format!(
+                    "{}{} g:{} l:{}",
+                    u.rule
+                        .name()
+                        .map_or(String::new(), |name| format!("{} ", name)),
+                    u.rule,
+                    u.generation,
+                    population
+                ))
+                .blended(if inverted {
+                    background_color
+                } else {
+                    font_color
+                })
                 .unwrap();
             let texture = texture_creator
                 .create_texture_from_surface(&surface)
@@ -97,25 +184,82 @@ pub fn render_universe<G: Grid>(mut u: Universe<G>) {
             let target = Rect::new(10, 5, width, height);
             canvas.copy(&texture, None, Some(target)).unwrap();
 
-            for row in 0..u.grid.nb_rows() {
-                for col in 0..u.grid.nb_columns() {
-                    if u.at(row, col) {
-                        let _ = canvas.fill_rect(Rect::new(
-                            col as i32 * cell_size as i32,
-                            row as i32 * cell_size as i32,
-                            cell_size,
-                            cell_size,
-                        ));
-                    }
+            // The rows of one-dimensional rules scroll up once the last one is at the bottom.
+            let scroll = match u.rule {
+                Rule::Wolfram(_) if !u.grid.is_bounded() => {
+                    i64::max(0, u.generation as i64 + 1 - u.grid.nb_rows() as i64)
+                }
+                _ => 0,
+            };
+            let rows = view.row + scroll..view.row + scroll + u.grid.nb_rows() as i64;
+            let cols = view.col..view.col + u.grid.nb_columns() as i64;
+            // Hexagonal grids are sheared around the middle row, each row being half a cell left of
+            // the one above, so that the 6 neighbours of a cell surround it.
+            let hexagonal = u.rule.shape() == Shape::Hexagonal;
+            let cell_rect = |RowCol { row, col }| {
+                (rows.contains(&row) && cols.contains(&col)).then(|| {
+                    let shear = if hexagonal {
+                        ((rows.start + rows.end) / 2 - row) as i32 * cell_size as i32 / 2
+                    } else {
+                        0
+                    };
+                    Rect::new(
+                        (col - cols.start) as i32 * cell_size as i32 + shear,
+                        (row - rows.start) as i32 * cell_size as i32,
+                        cell_size,
+                        cell_size,
+                    )
+                })
+            };
+
+            // Rectangles of cells of each state.
+            let mut rects = vec![vec![]; u.rule.nb_states() as usize];
+            for (rc, state) in u.grid.cell_states() {
+                if let (Some(rect), Some(rects)) = (cell_rect(rc), rects.get_mut(state as usize)) {
+                    rects.push(rect);
+                }
+            }
+
+            for (state, rects) in rects.iter().enumerate().skip(1) {
+                canvas.set_draw_color(if inverted {
+                    background_color
+                } else if matches!(u.rule, Rule::Lenia(_)) {
+                    value_color(state as u8)
+                } else {
+                    state_color(state as u8, u.rule.nb_states())
+                });
+                let _ = canvas.fill_rects(rects);
+            }
+
+            if let Some(delta) = u.delta().filter(|_| highlight) {
+                canvas.set_draw_color(birth_color);
+                for rect in delta.births.into_iter().filter_map(cell_rect) {
+                    let _ = canvas.fill_rect(rect);
+                }
+                canvas.set_draw_color(death_color);
+                for rect in delta.deaths.into_iter().filter_map(cell_rect) {
+                    let _ = canvas.fill_rect(rect);
+                }
+            }
+
+            // Ants are drawn bigger than cells, to be seen on large grids.
+            canvas.set_draw_color(ant_color);
+            for ant in &u.ants {
+                if let Some(rect) = cell_rect(ant.position) {
+                    let _ = canvas.fill_rect(Rect::from_center(
+                        rect.center(),
+                        3 * cell_size,
+                        3 * cell_size,
+                    ));
                 }
             }
 
             canvas.present();
 
-            if first {
-                first = false;
+            if redraw {
+                redraw = false;
             } else {
-                u = u.tick();
+                step(&mut u);
             }
         } else {
             std::thread::sleep(Duration::from_millis(100));
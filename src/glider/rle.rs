@@ -1,6 +1,8 @@
+use std::convert::TryFrom;
 use std::io::{self, Error, ErrorKind};
 use std::io::{BufRead, BufReader, Read};
 
+use super::grid::{BoundedGrid, RowCol, Topology};
 use super::rule::Rule;
 
 /* --------------------------------------------------------------------------------------------- */
@@ -10,6 +12,8 @@ pub enum RleEntry {
     Live(usize),
     Dead(usize),
     NewRow(usize),
+    /// Cells in a state from 2 on, for rules with more than 2 states.
+    State(usize, u8),
 }
 
 /* --------------------------------------------------------------------------------------------- */
@@ -17,6 +21,9 @@ pub enum RleEntry {
 #[derive(Debug)]
 pub struct Rle {
     pub pattern: Vec<RleEntry>,
+    /// Position of the top-left corner of the pattern, relatively to the centre of the grid, as
+    /// given by the `Pos` of an extended RLE header.
+    pub position: Option<RowCol>,
 }
 
 /* --------------------------------------------------------------------------------------------- */
@@ -37,7 +44,7 @@ impl Rle {
                     cols += nb;
                     max_cols = usize::max(cols, max_cols);
                 }
-                RleEntry::Dead(nb) => {
+                RleEntry::Dead(nb) | RleEntry::State(nb, _) => {
                     cols += nb;
                     max_cols = usize::max(cols, max_cols);
                 }
@@ -55,43 +62,59 @@ impl Rle {
         }
     }
 
-    pub fn read<R: Read>(reader: BufReader<R>) -> io::Result<(Self, Rule)> {
+    pub fn read<R: Read>(reader: BufReader<R>) -> io::Result<(Self, Rule, Option<BoundedGrid>)> {
         let mut pattern = vec![];
-        let mut rule_b = vec![];
-        let mut rule_s = vec![];
+        let mut parsed_rule = None;
+        let mut bounded_grid = None;
+        let mut position = None;
 
         'main_loop: for l in reader.lines() {
             let line = l?;
 
-            if line.is_empty() || line.starts_with('#') {
+            if line.starts_with("#CXRLE") {
+                position = Self::read_position(&line)?;
+            } else if line.is_empty() || line.starts_with('#') {
                 continue;
             } else if line.starts_with('x') {
-                let all: Vec<_> = line.split(|c| c == '=' || c == ',').collect();
-                if all.len() != 6 {
-                    return Err(Error::new(
-                        ErrorKind::InvalidData,
-                        format!("Unable to parse {}", line),
-                    ));
+                let invalid =
+                    || Error::new(ErrorKind::InvalidData, format!("Unable to parse {}", line));
+
+                // The rule comes last, and may contain commas.
+                let (dimension, rule) = match line.find("rule") {
+                    Some(i) => (&line[..i], Some(&line[i..])),
+                    None => (line.as_str(), None),
+                };
+
+                let all: Vec<_> = dimension
+                    .split(['=', ','])
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                if all.len() != 4 || all[0] != "x" || all[2] != "y" {
+                    return Err(invalid());
                 }
 
-                let r: Vec<_> = all[5].trim().split('/').collect();
-                if r.len() != 2 || !r[0].starts_with('B') || !r[1].starts_with('S') {
-                    return Err(Error::new(
-                        ErrorKind::InvalidData,
-                        format!("Unable to parse {}", line),
-                    ));
-                }
+                let rule = match rule.and_then(|r| r.split_once('=')) {
+                    Some((_, rule)) => rule.trim(),
+                    None if rule.is_none() => continue,
+                    None => return Err(invalid()),
+                };
 
-                for c in r[0].chars().skip(1) {
-                    rule_b.push(c.to_string().parse::<u8>().unwrap());
-                }
+                let rule = match rule.split_once(':') {
+                    Some((rule, bounds)) => {
+                        bounded_grid = Some(Self::read_bounded_grid(bounds)?);
+                        rule
+                    }
+                    None => rule,
+                };
 
-                for c in r[1].chars().skip(1) {
-                    rule_s.push(c.to_string().parse::<u8>().unwrap());
-                }
-                println!("Rule: B{:?}/S{:?}", rule_b, rule_s);
+                let rule = Rule::read(rule)?;
+                println!("Rule: {}", rule);
+                parsed_rule = Some(rule);
             } else {
                 let mut current_integer = String::from("");
+                // States from 25 on are written with a prefix from 'p' to 'y', as in pA.
+                let mut prefix = None;
                 for c in line.chars() {
                     match c {
                         '!' => {
@@ -100,18 +123,24 @@ impl Rle {
                         n if n.is_numeric() => {
                             current_integer.push(n);
                         }
+                        'p'..='y' if prefix.is_none() => {
+                            prefix = Some(c);
+                        }
                         c => {
+                            let invalid =
+                                || Error::new(ErrorKind::InvalidData, format!("Invalid '{}'", c));
+
                             let nb = current_integer.parse::<usize>().unwrap_or(1);
-                            pattern.push(match c {
-                                'o' => RleEntry::Live(nb),
-                                'b' => RleEntry::Dead(nb),
-                                '$' => RleEntry::NewRow(nb),
-                                x => {
-                                    return Err(Error::new(
-                                        ErrorKind::InvalidData,
-                                        format!("Invalid '{}'", x),
-                                    ))
+                            pattern.push(match (prefix.take(), c) {
+                                (None, 'o') | (None, 'A') => RleEntry::Live(nb),
+                                (None, 'b') | (None, '.') => RleEntry::Dead(nb),
+                                (None, '$') => RleEntry::NewRow(nb),
+                                (prefix, 'A'..='X') => {
+                                    let high = prefix.map_or(0, |p| p as usize - 'p' as usize + 1);
+                                    let state = 24 * high + c as usize - 'A' as usize + 1;
+                                    RleEntry::State(nb, u8::try_from(state).map_err(|_| invalid())?)
                                 }
+                                _ => return Err(invalid()),
                             });
                             current_integer.clear();
                         }
@@ -120,12 +149,92 @@ impl Rle {
             }
         }
 
-        if rule_b.is_empty() && rule_s.is_empty() {
+        let rule = parsed_rule.unwrap_or_else(|| {
             println!("Use default rule B3/S23");
-            Ok((Rle { pattern }, Rule::new(vec![3], vec![2, 3])))
-        } else {
-            Ok((Rle { pattern }, Rule::new(rule_b, rule_s)))
+            Rule::new(vec![3], vec![2, 3])
+        });
+
+        Ok((Rle { pattern, position }, rule, bounded_grid))
+    }
+
+    // Extended RLE header, such as #CXRLE Pos=-3,-4 Gen=12.
+    fn read_position(line: &str) -> io::Result<Option<RowCol>> {
+        let pos = match line.split_whitespace().find_map(|s| s.strip_prefix("Pos=")) {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+
+        let invalid = || Error::new(ErrorKind::InvalidData, format!("Invalid position {}", pos));
+
+        let (col, row) = pos.split_once(',').ok_or_else(invalid)?;
+
+        Ok(Some(RowCol {
+            row: row.parse().map_err(|_| invalid())?,
+            col: col.parse().map_err(|_| invalid())?,
+        }))
+    }
+
+    // Golly's bounded grid suffix, such as T64,64 or K10*,20.
+    fn read_bounded_grid(spec: &str) -> io::Result<BoundedGrid> {
+        let invalid = |reason: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid bounded grid {}: {}", spec, reason),
+            )
+        };
+
+        let mut chars = spec.trim().chars();
+        let kind = chars
+            .next()
+            .ok_or_else(|| invalid("empty"))?
+            .to_ascii_uppercase();
+        let sizes: Vec<_> = chars.as_str().split(',').collect();
+        if sizes.len() > 2 {
+            return Err(invalid("too many sizes"));
         }
+
+        // A '*' marks the twisted edges of a Klein bottle.
+        let twisted: Vec<_> = sizes.iter().map(|s| s.ends_with('*')).collect();
+        let sizes = sizes
+            .iter()
+            .map(|s| match s.trim_end_matches('*').parse::<usize>() {
+                Ok(0) => Err(invalid("unbounded sizes are not supported")),
+                Ok(size) => Ok(size),
+                Err(_) if s.contains(['+', '-']) => Err(invalid("shifts are not supported")),
+                Err(_) => Err(invalid("bad size")),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let nb_cols = sizes[0];
+        let nb_rows = *sizes.get(1).unwrap_or(&nb_cols);
+
+        let topology = match kind {
+            'P' => Topology::Plane,
+            'T' => Topology::Torus,
+            'K' => match twisted.as_slice() {
+                [true] | [true, false] => Topology::KleinBottle {
+                    horizontal_twist: true,
+                },
+                [false, true] => Topology::KleinBottle {
+                    horizontal_twist: false,
+                },
+                _ => return Err(invalid("exactly one size must be twisted")),
+            },
+            'C' => Topology::CrossSurface,
+            'S' if nb_rows == nb_cols => Topology::Sphere,
+            'S' => return Err(invalid("a sphere must be square")),
+            _ => return Err(invalid("unknown topology")),
+        };
+
+        if kind != 'K' && twisted.contains(&true) {
+            return Err(invalid("only a Klein bottle can be twisted"));
+        }
+
+        Ok(BoundedGrid {
+            topology,
+            nb_rows,
+            nb_cols,
+        })
     }
 }
 
@@ -134,13 +243,17 @@ impl Rle {
 #[test]
 fn test_dimension() {
     {
-        let rle = Rle { pattern: vec![] };
+        let rle = Rle {
+            pattern: vec![],
+            position: None,
+        };
 
         assert_eq!(rle.dimension(), (0, 0));
     }
     {
         let rle = Rle {
             pattern: vec![RleEntry::NewRow(10)],
+            position: None,
         };
 
         assert_eq!(rle.dimension(), (10, 0));
@@ -148,6 +261,7 @@ fn test_dimension() {
     {
         let rle = Rle {
             pattern: vec![RleEntry::Live(1), RleEntry::Dead(2)],
+            position: None,
         };
 
         assert_eq!(rle.dimension(), (1, 3));
@@ -155,6 +269,7 @@ fn test_dimension() {
     {
         let rle = Rle {
             pattern: vec![RleEntry::Live(1), RleEntry::Dead(2), RleEntry::NewRow(1)],
+            position: None,
         };
 
         assert_eq!(rle.dimension(), (1, 3));
@@ -172,6 +287,7 @@ fn test_dimension() {
                 RleEntry::Live(1),
                 RleEntry::NewRow(1),
             ],
+            position: None,
         };
 
         assert_eq!(rle.dimension(), (3, 3));
@@ -188,6 +304,7 @@ fn test_dimension() {
                 RleEntry::Dead(1),
                 RleEntry::Live(1),
             ],
+            position: None,
         };
 
         assert_eq!(rle.dimension(), (3, 3));
@@ -196,6 +313,139 @@ fn test_dimension() {
 
 /* --------------------------------------------------------------------------------------------- */
 
+#[test]
+fn read_bounded_grid() {
+    let bounds = |data: &str| {
+        Rle::read(BufReader::new(data.as_bytes()))
+            .map(|(_, _, bounded_grid)| bounded_grid)
+            .map_err(|_| ())
+    };
+
+    assert_eq!(bounds("x = 3, y = 3, rule = B3/S23\n3o!\n"), Ok(None));
+    assert_eq!(
+        bounds("x = 3, y = 3, rule = B3/S23:T64,32\n3o!\n"),
+        Ok(Some(BoundedGrid {
+            topology: Topology::Torus,
+            nb_rows: 32,
+            nb_cols: 64,
+        }))
+    );
+    assert_eq!(
+        bounds("x = 3, y = 3, rule = B3/S23:P20\n3o!\n"),
+        Ok(Some(BoundedGrid {
+            topology: Topology::Plane,
+            nb_rows: 20,
+            nb_cols: 20,
+        }))
+    );
+    assert_eq!(
+        bounds("x = 3, y = 3, rule = B3/S23:K10,20*\n3o!\n"),
+        Ok(Some(BoundedGrid {
+            topology: Topology::KleinBottle {
+                horizontal_twist: false
+            },
+            nb_rows: 20,
+            nb_cols: 10,
+        }))
+    );
+    assert_eq!(
+        bounds("x = 3, y = 3, rule = B3/S23:C8,9\n3o!\n"),
+        Ok(Some(BoundedGrid {
+            topology: Topology::CrossSurface,
+            nb_rows: 9,
+            nb_cols: 8,
+        }))
+    );
+    assert_eq!(
+        bounds("x = 3, y = 3, rule = B3/S23:S7\n3o!\n"),
+        Ok(Some(BoundedGrid {
+            topology: Topology::Sphere,
+            nb_rows: 7,
+            nb_cols: 7,
+        }))
+    );
+
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:S7,8\n3o!\n").is_err());
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:K10,20\n3o!\n").is_err());
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:T10*,20\n3o!\n").is_err());
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:T0,20\n3o!\n").is_err());
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:T10+1,20\n3o!\n").is_err());
+    assert!(bounds("x = 3, y = 3, rule = B3/S23:X10\n3o!\n").is_err());
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+#[test]
+fn read_position() {
+    let position = |data: &str| {
+        Rle::read(BufReader::new(data.as_bytes()))
+            .map(|(rle, _, _)| rle.position)
+            .map_err(|_| ())
+    };
+
+    assert_eq!(position("3o!\n"), Ok(None));
+    assert_eq!(position("#CXRLE Gen=3\n3o!\n"), Ok(None));
+    assert_eq!(
+        position("#CXRLE Pos=-3,12 Gen=3\n3o!\n"),
+        Ok(Some(RowCol { row: 12, col: -3 }))
+    );
+    assert!(position("#CXRLE Pos=-3\n3o!\n").is_err());
+    assert!(position("#CXRLE Pos=a,2\n3o!\n").is_err());
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+#[test]
+fn read_rule() {
+    let rule = |data: &str| {
+        Rle::read(BufReader::new(data.as_bytes()))
+            .map(|(_, rule, _)| rule.to_string())
+            .map_err(|_| ())
+    };
+
+    assert_eq!(rule("3o!\n"), Ok("B3/S23".to_string()));
+    assert_eq!(
+        rule("x = 3, y = 1, rule = b36s23\n3o!\n"),
+        Ok("B36/S23".to_string())
+    );
+    assert_eq!(
+        rule("x = 3, y = 1, rule = 23/3:T9\n3o!\n"),
+        Ok("B3/S23".to_string())
+    );
+    assert_eq!(
+        rule("x = 3, y = 1, rule = Day & Night\n3o!\n"),
+        Ok("B3678/S34678".to_string())
+    );
+    assert!(rule("x = 3, y = 1, rule = B9/S23\n3o!\n").is_err());
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+#[test]
+fn read_states() {
+    let pattern = |data: &str| {
+        Rle::read(BufReader::new(data.as_bytes()))
+            .map(|(rle, _, _)| rle.pattern)
+            .map_err(|_| ())
+    };
+
+    assert_eq!(
+        pattern("x = 5, y = 2, rule = B2/S/C3\n.A2B$pAyO!\n"),
+        Ok(vec![
+            RleEntry::Dead(1),
+            RleEntry::Live(1),
+            RleEntry::State(2, 2),
+            RleEntry::NewRow(1),
+            RleEntry::State(1, 25),
+            RleEntry::State(1, 255),
+        ])
+    );
+    assert!(pattern("yP!\n").is_err());
+    assert!(pattern("pb!\n").is_err());
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
 #[test]
 fn read_glider() {
     {
@@ -205,7 +455,10 @@ fn read_glider() {
         assert!(rle_read.is_err());
     }
     {
-        let rle = Rle { pattern: vec![] };
+        let rle = Rle {
+            pattern: vec![],
+            position: None,
+        };
         let data = "";
         let rle_read = Rle::read(BufReader::new(data.as_bytes()));
 
@@ -214,6 +467,7 @@ fn read_glider() {
     {
         let rle = Rle {
             pattern: vec![RleEntry::Live(3)],
+            position: None,
         };
 
         let data = "x = 3, y = 3, rule = B3/S23\n3o!\n";
@@ -224,6 +478,7 @@ fn read_glider() {
     {
         let rle = Rle {
             pattern: vec![RleEntry::NewRow(10)],
+            position: None,
         };
 
         let data = "#COMMENT\n10$!\n";
@@ -234,6 +489,7 @@ fn read_glider() {
     {
         let rle = Rle {
             pattern: vec![RleEntry::Dead(42)],
+            position: None,
         };
 
         let data = "\n42b\n";
@@ -252,6 +508,7 @@ fn read_glider() {
                 RleEntry::Dead(1),
                 RleEntry::Live(1),
             ],
+            position: None,
         };
 
         let data = "x = 3, y = 3, rule = B3/S23\n3o$2bo$bo!\n";
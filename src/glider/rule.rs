@@ -1,12 +1,345 @@
 /* --------------------------------------------------------------------------------------------- */
 
-#[derive(Clone, Copy, Debug)]
-pub struct Rule {
-    rule: [[bool; 9]; 2],
+use std::fmt;
+use std::io::{self, Error, ErrorKind};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::grid::{RowCol, NEIGHBOURS};
+use super::rule_table::RuleTable;
+use super::turmite::Turmite;
+
+/* --------------------------------------------------------------------------------------------- */
+
+// Well-known rules, which can be given by name.
+const NAMED_RULES: [(&str, &str); 14] = [
+    ("Life", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Seeds", "B2/S"),
+    ("Day & Night", "B3678/S34678"),
+    ("Maze", "B3/S12345"),
+    ("Replicator", "B1357/S1357"),
+    ("2x2", "B36/S125"),
+    ("Morley", "B368/S245"),
+    ("LongLife", "B345/S5"),
+    ("tlife", "B3/S2-i34q"),
+    ("Critters", "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0"),
+    ("Tron", "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0"),
+    ("BBM", "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15"),
+    ("Langton's Ant", "RL"),
+];
+
+// Hensel letters of neighbourhoods with 1 to 4 live neighbours, in canonical order, with a
+// neighbourhood of each letter's class.
+const LETTERS: [(&str, &[u8]); 4] = [
+    ("ce", &[1, 2]),
+    ("ceaikn", &[5, 10, 3, 24, 17, 36]),
+    ("ceaiknjqry", &[37, 26, 11, 7, 50, 13, 14, 38, 25, 49]),
+    (
+        "ceaiknjqrytwz",
+        &[165, 90, 15, 29, 51, 39, 58, 54, 27, 53, 57, 46, 60],
+    ),
+];
+
+// Hensel letters for a number of live neighbours, each with a neighbourhood of its class.
+// Neighbourhoods with 5 to 7 live neighbours take the letter of their complement.
+fn letters(count: u32) -> impl Iterator<Item = (char, u8)> {
+    let (letters, neighbourhoods) = match count {
+        1..=4 => LETTERS[count as usize - 1],
+        5..=7 => LETTERS[7 - count as usize],
+        _ => ("", &[][..]),
+    };
+
+    letters.chars().zip(
+        neighbourhoods
+            .iter()
+            .map(move |&n| if count > 4 { !n } else { n }),
+    )
+}
+
+// Smallest neighbourhood among the rotations and reflections of a neighbourhood.
+fn canonical(neighbourhood: u8) -> u8 {
+    let transform = |n: u8, f: fn((i64, i64)) -> (i64, i64)| {
+        (0..8).filter(|i| n & (1 << i) != 0).fold(0u8, |acc, i| {
+            let image = f(NEIGHBOURS[i]);
+            acc | 1 << NEIGHBOURS.iter().position(|&p| p == image).unwrap()
+        })
+    };
+
+    let mut min = neighbourhood;
+    for mut n in [neighbourhood, transform(neighbourhood, |(r, c)| (r, -c))] {
+        for _ in 0..4 {
+            min = u8::min(min, n);
+            n = transform(n, |(r, c)| (c, -r));
+        }
+    }
+
+    min
+}
+
+// Ranks of the Hensel letters of a number of live neighbours, 4 bits each following `letters`:
+// the letters of `first` in their order, then the others.
+fn letter_ranks(count: u32, first: &str) -> u64 {
+    let mut order = vec![];
+    for l in first.chars().chain(letters(count).map(|(l, _)| l)) {
+        if !order.contains(&l) {
+            order.push(l);
+        }
+    }
+
+    order.iter().enumerate().fold(0, |ranks, (rank, &l)| {
+        let i = letters(count).position(|(letter, _)| letter == l).unwrap();
+        ranks | (rank as u64) << (4 * i)
+    })
+}
+
+// Hensel letter of a neighbourhood, if there are several classes with its number of neighbours.
+fn letter(neighbourhood: u8) -> Option<char> {
+    let class = canonical(neighbourhood);
+
+    letters(neighbourhood.count_ones())
+        .find(|&(_, n)| canonical(n) == class)
+        .map(|(letter, _)| letter)
+}
+
+// A neighbourhood with `count` live neighbours.
+fn any_neighbourhood(count: u8) -> u8 {
+    ((1u16 << count) - 1) as u8
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Index in MAP rules of a cell and its neighbourhood: the 3x3 square as a number, in row-major
+// order from the highest bit.
+fn map_index(previous: bool, neighbourhood: u8) -> usize {
+    let bit = |i: usize| (neighbourhood >> i & 1) as usize;
+
+    bit(0) << 8
+        | bit(1) << 7
+        | bit(2) << 6
+        | bit(3) << 5
+        | (previous as usize) << 4
+        | bit(4) << 3
+        | bit(5) << 2
+        | bit(6) << 1
+        | bit(7)
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// How cells change from a generation to the next, for each family of rules. With more than 2
+/// states, live cells which don't survive go through dying states before being dead, as in
+/// Golly's Generations rules.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Rule {
+    LifeLike(LifeLike),
+    LargerThanLife(LargerThanLife),
+    /// A rule of a Golly `.rule` file.
+    Table(Arc<RuleTable>),
+    Margolus(Margolus),
+    Wolfram(Wolfram),
+    /// A turmite, which moves ants rather than computing the next state of cells.
+    Turmite(Arc<Turmite>),
+    /// A Lenia rule, whose cells take values from 0 to 1 (see `LeniaGrid`).
+    Lenia(Lenia),
+}
+
+/// Whether a cell lives at the next generation, given its state and its neighbourhood (see
+/// `Grid::neighbourhood`). Totalistic rules only depend on the number of live neighbours, while
+/// isotropic non-totalistic ones also depend on their arrangement, up to rotations and
+/// reflections.
+#[derive(Clone, Copy, Debug, Eq)]
+pub struct LifeLike {
+    // Bit sets of neighbourhoods where dead (0) and live (1) cells live.
+    table: [[u128; 2]; 2],
+    totalistic: bool,
+    nb_states: u8,
+    shape: Shape,
+    // Ranks of the Hensel letters for dead (0) and live (1) cells and each number of live
+    // neighbours (see `letter_ranks`), to print them in the order they were parsed.
+    letter_ranks: [[u64; 9]; 2],
+}
+
+// Rules are the same whatever the order of their Hensel letters.
+impl PartialEq for LifeLike {
+    fn eq(&self, other: &Self) -> bool {
+        (self.table, self.totalistic, self.nb_states, self.shape)
+            == (other.table, other.totalistic, other.nb_states, other.shape)
+    }
+}
+
+/// Shape of the neighbourhood, of range 1 unless it's a Larger than Life rule.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Shape {
+    /// Cells at most `radius` rows and columns away.
+    Moore,
+    /// Cells at most `radius` steps away, counting rows and columns.
+    VonNeumann,
+    /// As in Golly, a hexagonal grid is a square one where cells are neighbours of the cells
+    /// above-left and below-right, but not of the cells above-right and below-left.
+    Hexagonal,
+}
+
+impl Shape {
+    /// Neighbours of range 1 in this shape, as a bit set following `NEIGHBOURS`.
+    pub fn neighbours(&self) -> u8 {
+        match self {
+            Shape::Moore => 0b1111_1111,
+            Shape::VonNeumann => 0b0101_1010,
+            Shape::Hexagonal => 0b1101_1011,
+        }
+    }
+
+    // Rule suffix of this shape.
+    fn suffix(&self) -> &'static str {
+        match self {
+            Shape::Moore => "",
+            Shape::VonNeumann => "V",
+            Shape::Hexagonal => "H",
+        }
+    }
+}
+
+/// A Larger than Life rule, in Golly's `R5,C0,M1,S34..58,B34..45,NM` notation: a cell lives if
+/// the number of live cells within `radius` is in the survival or birth range.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LargerThanLife {
+    pub radius: u16,
+    pub nb_states: u8,
+    /// Whether a cell counts itself.
+    pub middle: bool,
+    pub survival: (u32, u32),
+    pub birth: (u32, u32),
+    pub shape: Shape,
+}
+
+impl LargerThanLife {
+    pub fn lives(&self, previous: bool, nb_live_cells: u32) -> bool {
+        let (min, max) = if previous { self.survival } else { self.birth };
+
+        (min..=max).contains(&nb_live_cells)
+    }
+}
+
+/// A block rule, in MCell's `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15` notation: the grid is
+/// split in 2x2 blocks, which become the block of `blocks` at the index of their live cells, the
+/// top-left one being 1, the top-right one 2, the bottom-left one 4 and the bottom-right one 8.
+/// Blocks start at even rows and columns at even generations, and at odd ones otherwise.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Margolus {
+    blocks: [u8; 16],
+    /// Whether blocks start at odd rows and columns.
+    odd: bool,
+}
+
+impl Margolus {
+    /// Whether the cell at `rc` lives at the next generation, given its state and its
+    /// neighbourhood, which includes the other cells of its block.
+    pub fn lives(&self, rc: RowCol, previous: bool, neighbourhood: u8) -> bool {
+        let offset = self.odd as i64;
+        let (row, col) = (
+            (rc.row - offset).rem_euclid(2),
+            (rc.col - offset).rem_euclid(2),
+        );
+
+        let mut block = (previous as usize) << (2 * row + col);
+        for (i, &(dr, dc)) in NEIGHBOURS.iter().enumerate() {
+            let (r, c) = (row + dr, col + dc);
+            if (0..2).contains(&r) && (0..2).contains(&c) {
+                block |= (neighbourhood as usize >> i & 1) << (2 * r + c);
+            }
+        }
+
+        self.blocks[block] >> (2 * row + col) & 1 != 0
+    }
+}
+
+/// A Lenia rule, written `Lenia(R13,T10,m0.15,s0.015)`: cells take values from 0 to 1, and
+/// grow at each generation by `1 / time` times the growth of the weighted sum of the cells within
+/// `radius`, weights forming a ring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lenia {
+    pub radius: u16,
+    pub time: u16,
+    /// Sum of the cells around for which they grow the most.
+    pub mu: f32,
+    /// Width of the growth's bell curve.
+    pub sigma: f32,
+}
+
+// Parameters are finite numbers.
+impl Eq for Lenia {}
+
+impl Lenia {
+    /// Weight of cells at `distance`, not normalised, which peaks half-way to `radius`.
+    pub fn kernel(&self, distance: f32) -> f32 {
+        let r = distance / self.radius as f32;
+        if r <= 0.0 || r >= 1.0 {
+            return 0.0;
+        }
+
+        (4.0 - 1.0 / (r * (1.0 - r))).exp()
+    }
+
+    /// Growth of a cell, from -1 to 1, given the weighted sum of the cells around it.
+    pub fn growth(&self, potential: f32) -> f32 {
+        let x = (potential - self.mu) / self.sigma;
+
+        2.0 * (-x * x / 2.0).exp() - 1.0
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// An elementary one-dimensional rule, in Golly's `W30` notation. Each row of the grid is a
+/// generation, the first one being row 0, so that a generation adds a row below the others.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Wolfram {
+    number: u8,
+    /// Row computed at the next generation.
+    row: i64,
+}
+
+impl Wolfram {
+    /// Row computed at the next generation. Other rows stay the same.
+    pub fn row(&self) -> i64 {
+        self.row
+    }
+
+    /// The rule of the previous generation, which computed the row above.
+    pub fn previous(&self) -> Self {
+        Wolfram {
+            row: self.row - 1,
+            ..*self
+        }
+    }
+
+    /// Whether a cell of `row` lives, given the cells above-left, above and above-right.
+    pub fn lives(&self, above: [bool; 3]) -> bool {
+        let index = (above[0] as u8) << 2 | (above[1] as u8) << 1 | above[2] as u8;
+
+        self.number >> index & 1 != 0
+    }
 }
 
 /* --------------------------------------------------------------------------------------------- */
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseRuleError {
+    rule: String,
+    reason: &'static str,
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid rule {}: {}", self.rule, self.reason)
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+/* --------------------------------------------------------------------------------------------- */
+
 impl Rule {
     pub fn new(birth: Vec<u8>, survival: Vec<u8>) -> Self {
         let mut b = [false; 9];
@@ -20,12 +353,992 @@ impl Rule {
             s[i as usize] = true;
         }
 
-        Rule { rule: [b, s] }
+        Rule::LifeLike(LifeLike::from_fn(|previous, n| {
+            [b, s][previous as usize][n.count_ones() as usize]
+        }))
+    }
+
+    /// Number of states of cells, dead and live ones included.
+    pub fn nb_states(&self) -> u8 {
+        match self {
+            Rule::LifeLike(life) => life.nb_states,
+            Rule::LargerThanLife(ltl) => ltl.nb_states,
+            Rule::Table(rule_table) => rule_table.nb_states,
+            Rule::Margolus(_) | Rule::Wolfram(_) => 2,
+            // Each colour is a state.
+            Rule::Turmite(turmite) => turmite.nb_colours(),
+            Rule::Lenia(_) => 255,
+        }
+    }
+
+    /// Shape of the neighbourhood. Rules with another shape than `Moore` ignore the other
+    /// neighbours, and are therefore not totalistic.
+    pub fn shape(&self) -> Shape {
+        match self {
+            Rule::LifeLike(life) => life.shape,
+            Rule::LargerThanLife(ltl) => ltl.shape,
+            Rule::Table(rule_table) => rule_table.shape,
+            _ => Shape::Moore,
+        }
+    }
+
+    /// The rule given by a rulestring or a name, or, as in Golly, by the `.rule` file of this
+    /// name in the current directory or in `rules/`.
+    pub fn read(rule: &str) -> io::Result<Self> {
+        match rule.parse() {
+            Ok(rule) => Ok(rule),
+            Err(e) => match RuleTable::load(rule) {
+                Ok(rule_table) => Ok(Rule::Table(Arc::new(rule_table))),
+                Err(load) if load.kind() == ErrorKind::NotFound => {
+                    Err(Error::new(ErrorKind::InvalidData, e.to_string()))
+                }
+                Err(load) => Err(load),
+            },
+        }
+    }
+
+    /// The name of this rule, if it's a well-known one such as `HighLife`.
+    pub fn name(&self) -> Option<&'static str> {
+        // Named rules are written in canonical form.
+        let rule = self.to_string();
+
+        NAMED_RULES
+            .iter()
+            .find(|(_, named)| *named == rule)
+            .map(|(name, _)| *name)
+    }
+
+    /// The rule computing the next generation from the grid at `generation`. As in Golly, rules
+    /// where dead cells with no live neighbours are born (B0) are emulated with rules without B0
+    /// on the complement of the grid, at every other generation if live cells with 8 live
+    /// neighbours die, and from generation 1 on otherwise. See `is_inverted`.
+    pub fn at_generation(&self, generation: u64) -> Rule {
+        self.at_generation_from(
+            generation,
+            self.is_inverted(generation),
+            self.is_inverted(generation + 1),
+        )
+    }
+
+    /// Same as `at_generation` for bounded grids, on which B0 rules aren't emulated: cells beyond
+    /// their borders are dead rather than of the background.
+    pub fn at_bounded_generation(&self, generation: u64) -> Rule {
+        self.at_generation_from(generation, false, false)
+    }
+
+    // The rule of `at_generation`, `from` and `to` telling whether the grid is complemented before
+    // and after this generation.
+    fn at_generation_from(&self, generation: u64, from: bool, to: bool) -> Rule {
+        match self {
+            Rule::Wolfram(wolfram) => Rule::Wolfram(Wolfram {
+                row: generation as i64 + 1,
+                ..*wolfram
+            }),
+            Rule::Margolus(margolus) => {
+                let mut blocks = [0; 16];
+                for (block, next) in blocks.iter_mut().enumerate() {
+                    let block = if from { block ^ 15 } else { block };
+                    *next = if to {
+                        margolus.blocks[block] ^ 15
+                    } else {
+                        margolus.blocks[block]
+                    };
+                }
+                Rule::Margolus(Margolus {
+                    blocks,
+                    odd: generation % 2 == 1,
+                })
+            }
+            Rule::LifeLike(life) if life.lives_in(false, 0) => Rule::LifeLike(LifeLike {
+                nb_states: life.nb_states,
+                shape: life.shape,
+                ..LifeLike::from_fn(|previous, n| {
+                    let (previous, n) = if from { (!previous, !n) } else { (previous, n) };
+                    life.lives_in(previous, n) != to
+                })
+            }),
+            _ => self.clone(),
+        }
+    }
+
+    /// Whether the grid at `generation` holds the complement of cells, the background being
+    /// alive. Always false for rules without B0.
+    pub fn is_inverted(&self, generation: u64) -> bool {
+        // Empty and full blocks of block rules.
+        let (empty, full) = match self {
+            Rule::Margolus(margolus) => (margolus.blocks[0] == 15, margolus.blocks[15] == 15),
+            Rule::LifeLike(life) => (life.lives_in(false, 0), life.lives_in(true, 0xff)),
+            _ => (false, false),
+        };
+
+        if !empty {
+            false
+        } else if full {
+            generation > 0
+        } else {
+            generation % 2 == 1
+        }
+    }
+
+    /// The state of a cell at the next generation, given whether `lives` or `lives_in` tells it
+    /// lives. Dying cells always go on dying.
+    pub fn next_state(&self, state: u8, lives: bool) -> u8 {
+        match state {
+            0 | 1 if lives => 1,
+            0 => 0,
+            _ if state + 1 < self.nb_states() => state + 1,
+            _ => 0,
+        }
+    }
+
+    // Neighbourhoods of a B or S section such as 2-a34ity, where a digit alone stands for all
+    // neighbourhoods with this number of live neighbours.
+    fn read_counts(
+        counts: &str,
+        outcomes: &mut [bool; 256],
+        ranks: &mut [u64; 9],
+    ) -> Result<(), &'static str> {
+        let mut chars = counts.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let count = c.to_digit(10).ok_or("unexpected character")?;
+            if count > 8 {
+                return Err("at most 8 neighbours");
+            }
+
+            let negated = chars.next_if_eq(&'-').is_some();
+            let mut selected = String::new();
+            while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+                selected.push(letter.to_ascii_lowercase());
+            }
+
+            if negated && selected.is_empty() {
+                return Err("expected letters after '-'");
+            }
+            if selected
+                .chars()
+                .any(|l| !letters(count).any(|(letter, _)| letter == l))
+            {
+                return Err("unknown letter");
+            }
+            if !selected.is_empty() {
+                ranks[count as usize] = letter_ranks(count, &selected);
+            }
+
+            for n in (0..=255u8).filter(|n| n.count_ones() == count) {
+                outcomes[n as usize] |= match letter(n) {
+                    Some(l) if !selected.is_empty() => selected.contains(l) != negated,
+                    _ => true,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    // A MAP rule, whose 512 bits, in base64, tell whether a cell lives for each 3x3 square
+    // around it.
+    fn read_map(map: &str) -> Result<Self, &'static str> {
+        let mut bits = vec![];
+        for c in map.trim_end_matches('=').bytes() {
+            let value = BASE64
+                .iter()
+                .position(|&b| b == c)
+                .ok_or("unexpected character")?;
+            bits.extend((0..6).rev().map(|i| value >> i & 1 != 0));
+        }
+        // The last 4 bits only pad the 512 ones.
+        if bits.len() != 516 {
+            return Err("expected 512 bits");
+        }
+
+        Ok(Rule::LifeLike(LifeLike::from_fn(|previous, n| {
+            bits[map_index(previous, n)]
+        })))
+    }
+
+    // A block rule such as MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15.
+    fn read_margolus(blocks: &str) -> Result<Self, &'static str> {
+        let mut margolus = Margolus {
+            blocks: [0; 16],
+            odd: false,
+        };
+
+        let mut values = blocks.split(';');
+        for block in margolus.blocks.iter_mut() {
+            *block = match values.next().map(|value| value.trim().parse()) {
+                Some(Ok(value)) if value < 16 => value,
+                Some(_) => return Err("expected blocks from 0 to 15"),
+                None => return Err("expected 16 blocks"),
+            };
+        }
+        if values.next().is_some() {
+            return Err("expected 16 blocks");
+        }
+        // Otherwise the background would change to some other pattern.
+        if !matches!(margolus.blocks[0], 0 | 15) {
+            return Err("empty blocks must become empty or full");
+        }
+
+        Ok(Rule::Margolus(margolus))
+    }
+
+    // An elementary one-dimensional rule such as W30.
+    fn read_wolfram(number: &str) -> Result<Self, &'static str> {
+        let number = number
+            .parse()
+            .map_err(|_| "expected a number from 0 to 255")?;
+
+        Ok(Rule::Wolfram(Wolfram { number, row: 1 }))
+    }
+
+    // A Lenia rule such as Lenia(R13,T10,m0.15,s0.015).
+    fn read_lenia(parameters: &str) -> Result<Self, &'static str> {
+        let parameters: Vec<_> = parameters.split(',').map(str::trim).collect();
+        let (r, t, m, s) = match parameters[..] {
+            [r, t, m, s] => (r, t, m, s),
+            _ => return Err("expected R,T,m,s"),
+        };
+
+        let radius = match r.strip_prefix('R').and_then(|r| r.parse().ok()) {
+            Some(radius @ 1..=100) => radius,
+            _ => return Err("expected a radius from 1 to 100"),
+        };
+        let time = match t.strip_prefix('T').and_then(|t| t.parse().ok()) {
+            Some(time @ 1..=1000) => time,
+            _ => return Err("expected a time from 1 to 1000"),
+        };
+        let number = |part: &str, prefix| {
+            let value: f32 = part.strip_prefix(prefix)?.parse().ok()?;
+            (value.is_finite() && value > 0.0).then_some(value)
+        };
+        let mu = number(m, 'm').ok_or("expected m followed by a positive number")?;
+        let sigma = number(s, 's').ok_or("expected s followed by a positive number")?;
+
+        Ok(Rule::Lenia(Lenia {
+            radius,
+            time,
+            mu,
+            sigma,
+        }))
+    }
+
+    // A turmite such as RL or {{{1,2,0},{0,8,0}}}.
+    fn read_turmite(turmite: &str) -> Result<Self, &'static str> {
+        Ok(Rule::Turmite(Arc::new(turmite.parse()?)))
     }
 
+    // A Larger than Life rule such as R5,C0,M1,S34..58,B34..45,NM.
+    fn read_larger_than_life(rule: &str) -> Result<Self, &'static str> {
+        let parts: Vec<_> = rule.split(',').collect();
+        let (r, c, m, s, b, n) = match parts[..] {
+            [r, c, m, s, b, n] => (r, c, m, s, b, n),
+            _ => return Err("expected R,C,M,S,B,N"),
+        };
+
+        fn value(part: &str, prefix: char) -> Option<&str> {
+            part.strip_prefix([prefix, prefix.to_ascii_lowercase()])
+        }
+        let number = |part, prefix| value(part, prefix).and_then(|v| v.parse::<u32>().ok());
+        let range = |part, prefix| -> Option<(u32, u32)> {
+            let (min, max) = value(part, prefix)?.split_once("..")?;
+            Some((min.parse().ok()?, max.parse().ok()?))
+        };
+
+        let radius = match number(r, 'R') {
+            Some(radius @ 1..=500) => radius as u16,
+            _ => return Err("expected a radius from 1 to 500"),
+        };
+        let nb_states = match number(c, 'C') {
+            Some(0..=2) => 2,
+            Some(n @ 3..=255) => n as u8,
+            _ => return Err("expected a number of states up to 255"),
+        };
+        let middle = match number(m, 'M') {
+            Some(m @ 0..=1) => m == 1,
+            _ => return Err("expected M0 or M1"),
+        };
+        let survival = range(s, 'S').ok_or("expected S followed by a range")?;
+        let birth = range(b, 'B').ok_or("expected B followed by a range")?;
+        let shape = match value(n, 'N') {
+            Some("M" | "m") => Shape::Moore,
+            Some("N" | "n") => Shape::VonNeumann,
+            Some("H" | "h") => Shape::Hexagonal,
+            _ => return Err("expected NM, NN or NH"),
+        };
+
+        Ok(Rule::LargerThanLife(LargerThanLife {
+            radius,
+            nb_states,
+            middle,
+            survival,
+            birth,
+            shape,
+        }))
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl LifeLike {
+    // A rule from the outcome of each state and neighbourhood.
+    fn from_fn(lives: impl Fn(bool, u8) -> bool) -> Self {
+        let mut table = [[0; 2]; 2];
+
+        for (previous, bits) in table.iter_mut().enumerate() {
+            for n in 0..=255u8 {
+                if lives(previous == 1, n) {
+                    bits[n as usize / 128] |= 1 << (n % 128);
+                }
+            }
+        }
+
+        let mut life = LifeLike {
+            table,
+            totalistic: false,
+            nb_states: 2,
+            shape: Shape::Moore,
+            letter_ranks: [[0; 9]; 2],
+        };
+        life.totalistic = (0..=255u8).all(|n| {
+            let any = any_neighbourhood(n.count_ones() as u8);
+            [false, true]
+                .iter()
+                .all(|&previous| life.lives_in(previous, n) == life.lives_in(previous, any))
+        });
+
+        life
+    }
+
+    /// Whether the rule only depends on the number of live neighbours, in which case `lives` can
+    /// be used instead of `lives_in`.
+    pub fn is_totalistic(&self) -> bool {
+        self.totalistic
+    }
+
+    /// For totalistic rules.
     pub fn lives(&self, previous: bool, nb_live_neighbors: u8) -> bool {
-        self.rule[previous as usize][nb_live_neighbors as usize]
+        self.lives_in(previous, any_neighbourhood(nb_live_neighbors))
+    }
+
+    pub fn lives_in(&self, previous: bool, neighbourhood: u8) -> bool {
+        let bits = self.table[previous as usize][neighbourhood as usize / 128];
+
+        (bits >> (neighbourhood % 128)) & 1 != 0
+    }
+
+    // Whether the rule is the same for rotated and reflected neighbourhoods.
+    fn is_isotropic(&self) -> bool {
+        (0..=255u8).all(|n| {
+            let class = canonical(n);
+            self.lives_in(false, n) == self.lives_in(false, class)
+                && self.lives_in(true, n) == self.lives_in(true, class)
+        })
     }
 }
 
 /* --------------------------------------------------------------------------------------------- */
+
+/// Parses `B36/S23`, `S23/B36`, Golly's `b36s23`, or the classic survival/birth form `23/36`.
+/// Numbers of neighbours may be followed by Hensel letters, as in `B2-a/S12`, and Generations
+/// rules add a number of states, as in `B2/S/C3` or `345/2/4`. Larger than Life rules are written
+/// as in Golly, e.g. `R5,C0,M1,S34..58,B34..45,NM`. A `V` or `H` suffix, as in `B2/S013V` or
+/// `B2/S34H`, selects the von Neumann or hexagonal neighbourhood. Golly's `MAP` rules give the
+/// outcome of each 3x3 square in base64, and may not be isotropic. Well-known rules can also be
+/// given by name, such as `HighLife` or `Day & Night`. Block rules are written as in MCell, e.g.
+/// `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15`, and one-dimensional rules and turmites as in
+/// Golly, e.g. `W30` and `RL` or `{{{1,2,0},{0,8,0}}}`. Lenia rules are written
+/// `Lenia(R13,T10,m0.15,s0.015)`.
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+
+        let rule = s.trim();
+        if let Some(parameters) = rule
+            .strip_prefix("Lenia(")
+            .and_then(|r| r.strip_suffix(')'))
+        {
+            return Self::read_lenia(parameters).map_err(invalid);
+        }
+        if let Some((_, rule)) = NAMED_RULES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(rule))
+        {
+            return rule.parse();
+        }
+        if rule.starts_with('{') || rule.len() > 1 && rule.chars().all(|c| "LRNUlrnu".contains(c)) {
+            return Self::read_turmite(rule).map_err(invalid);
+        }
+        if rule.starts_with(['R', 'r']) {
+            return Self::read_larger_than_life(rule).map_err(invalid);
+        }
+        if let Some(map) = rule.strip_prefix("MAP") {
+            return Self::read_map(map).map_err(invalid);
+        }
+        if let Some(blocks) = rule.strip_prefix("MS,D") {
+            return Self::read_margolus(blocks).map_err(invalid);
+        }
+        if let Some(number) = rule.strip_prefix(['W', 'w']) {
+            return Self::read_wolfram(number).map_err(invalid);
+        }
+
+        // Von Neumann and hexagonal rules end with V or H.
+        let shape = match rule.chars().last() {
+            Some('V' | 'v') => Shape::VonNeumann,
+            Some('H' | 'h') => Shape::Hexagonal,
+            _ => Shape::Moore,
+        };
+        let rule = match shape {
+            Shape::Moore => rule,
+            _ => &rule[..rule.len() - 1],
+        };
+
+        let mut parts: Vec<_> = rule.split('/').collect();
+        if rule.is_empty() {
+            return Err(invalid("empty"));
+        }
+
+        // Generations rules end with their number of states, as in B2/S/C3 or 345/2/4.
+        let mut nb_states = None;
+        if parts.len() == 3 {
+            let states = parts.pop().unwrap();
+            match states.trim_start_matches(['C', 'c']).parse() {
+                Ok(n) if n >= 2 => nb_states = Some((n, states.starts_with(['C', 'c']))),
+                _ => return Err(invalid("expected a number of states from 2 to 255")),
+            }
+        }
+        if parts.len() > 2 {
+            return Err(invalid("too many '/'"));
+        }
+
+        let is_section = |c: char| matches!(c, 'B' | 'S' | 'b' | 's');
+        let mut table = [[false; 256]; 2];
+        let mut letter_ranks = [[0; 9]; 2];
+        let with_table = |table: [[bool; 256]; 2], letter_ranks| {
+            let neighbours = shape.neighbours();
+            let totalistic = |n: u8| any_neighbourhood(n.count_ones() as u8) as usize;
+
+            if shape != Shape::Moore {
+                if (0..=255u8).any(|n| table.iter().any(|t| t[n as usize] != t[totalistic(n)])) {
+                    return Err(invalid("no Hensel letters with V or H"));
+                }
+                if (neighbours.count_ones() + 1..=8).any(|count| {
+                    table
+                        .iter()
+                        .any(|t| t[any_neighbourhood(count as u8) as usize])
+                }) {
+                    return Err(invalid("too many neighbours for V or H"));
+                }
+            }
+
+            if nb_states.is_some() && table[0][0] {
+                return Err(invalid("no B0 with more than 2 states"));
+            }
+
+            Ok(Rule::LifeLike(LifeLike {
+                nb_states: nb_states.map_or(2, |(n, _)| n),
+                shape,
+                letter_ranks,
+                ..LifeLike::from_fn(|previous, n| match shape {
+                    Shape::Moore => table[previous as usize][n as usize],
+                    _ => table[previous as usize][totalistic(n & neighbours)],
+                })
+            }))
+        };
+
+        if !parts.concat().contains(is_section) {
+            if parts.len() != 2 {
+                return Err(invalid("expected survival/birth"));
+            }
+            for (part, index) in parts.iter().zip([1, 0]) {
+                Self::read_counts(part, &mut table[index], &mut letter_ranks[index])
+                    .map_err(invalid)?;
+            }
+
+            return with_table(table, letter_ranks);
+        }
+
+        if parts.iter().any(|p| !p.starts_with(is_section)) {
+            return Err(invalid("expected B and S"));
+        }
+        if let Some((_, false)) = nb_states {
+            return Err(invalid("expected C before the number of states"));
+        }
+
+        // Sections start at each B or S.
+        let rule = parts.concat();
+        let starts: Vec<_> = rule
+            .match_indices(is_section)
+            .map(|(i, _)| i)
+            .chain([rule.len()])
+            .collect();
+        let mut seen = [false; 2];
+
+        for section in starts.windows(2) {
+            let index = rule[section[0]..].starts_with(['S', 's']) as usize;
+            if seen[index] {
+                return Err(invalid("duplicate B or S"));
+            }
+            seen[index] = true;
+
+            Self::read_counts(
+                &rule[section[0] + 1..section[1]],
+                &mut table[index],
+                &mut letter_ranks[index],
+            )
+            .map_err(invalid)?;
+        }
+
+        with_table(table, letter_ranks)
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// The notation each family of rules is parsed from, the name of rule tables included.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::LifeLike(life) => write!(f, "{}", life),
+            Rule::LargerThanLife(ltl) => write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                ltl.radius,
+                if ltl.nb_states > 2 { ltl.nb_states } else { 0 },
+                ltl.middle as u8,
+                ltl.survival.0,
+                ltl.survival.1,
+                ltl.birth.0,
+                ltl.birth.1,
+                match ltl.shape {
+                    Shape::Moore => 'M',
+                    Shape::VonNeumann => 'N',
+                    Shape::Hexagonal => 'H',
+                }
+            ),
+            Rule::Table(rule_table) => write!(f, "{}", rule_table.name),
+            Rule::Margolus(margolus) => {
+                let blocks: Vec<_> = margolus.blocks.iter().map(|b| b.to_string()).collect();
+                write!(f, "MS,D{}", blocks.join(";"))
+            }
+            Rule::Wolfram(wolfram) => write!(f, "W{}", wolfram.number),
+            Rule::Turmite(turmite) => write!(f, "{}", turmite),
+            Rule::Lenia(lenia) => write!(
+                f,
+                "Lenia(R{},T{},m{},s{})",
+                lenia.radius, lenia.time, lenia.mu, lenia.sigma
+            ),
+        }
+    }
+}
+
+/// The canonical `B36/S23` form, with the shortest Hensel letters such as `B2-a/S12` in the order
+/// they were parsed, or a `MAP` rule if it isn't isotropic.
+impl fmt::Display for LifeLike {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.shape == Shape::Moore && !self.is_isotropic() {
+            let mut bits = vec![false; 516];
+            for n in 0..=255u8 {
+                bits[map_index(false, n)] = self.lives_in(false, n);
+                bits[map_index(true, n)] = self.lives_in(true, n);
+            }
+            let map: String = bits
+                .chunks(6)
+                .map(|chunk| {
+                    let value = chunk.iter().fold(0, |acc, &bit| acc << 1 | bit as usize);
+                    BASE64[value] as char
+                })
+                .collect();
+            return write!(f, "MAP{}", map);
+        }
+
+        let section = |previous: bool| -> String {
+            let mut section = String::new();
+
+            if self.shape != Shape::Moore {
+                let neighbours = self.shape.neighbours();
+                for count in 0..=neighbours.count_ones() {
+                    // The first `count` neighbours of the shape.
+                    let n = (0..8)
+                        .filter(|i| neighbours & 1 << i != 0)
+                        .take(count as usize)
+                        .fold(0u8, |acc, i| acc | 1 << i);
+                    if self.lives_in(previous, n) {
+                        section.push(char::from_digit(count, 10).unwrap());
+                    }
+                }
+
+                return section;
+            }
+
+            for count in 0..=8u32 {
+                let digit = char::from_digit(count, 10).unwrap();
+                let (live, dead): (Vec<_>, Vec<_>) =
+                    letters(count).partition(|&(_, n)| self.lives_in(previous, n));
+                let ranks = self.letter_ranks[previous as usize][count as usize];
+                let names = |chosen: Vec<(char, u8)>| {
+                    let mut names: Vec<_> = chosen.into_iter().map(|(l, _)| l).collect();
+                    names.sort_by_key(|&l| {
+                        let i = letters(count).position(|(letter, _)| letter == l);
+                        ranks >> (4 * i.unwrap()) & 0xf
+                    });
+                    names
+                };
+
+                match (live.len(), dead.len()) {
+                    (0, 0) if self.lives_in(previous, any_neighbourhood(count as u8)) => {
+                        section.push(digit)
+                    }
+                    (0, _) => {}
+                    (_, 0) => section.push(digit),
+                    (l, d) if l <= d => {
+                        section.push(digit);
+                        section.extend(names(live));
+                    }
+                    _ => {
+                        section.push(digit);
+                        section.push('-');
+                        section.extend(names(dead));
+                    }
+                }
+            }
+
+            section
+        };
+
+        write!(f, "B{}/S{}", section(false), section(true))?;
+        if self.nb_states > 2 {
+            write!(f, "/C{}", self.nb_states)?;
+        }
+        write!(f, "{}", self.shape.suffix())?;
+
+        Ok(())
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn life_like(rule: Rule) -> LifeLike {
+        match rule {
+            Rule::LifeLike(life) => life,
+            _ => panic!("{} isn't life-like", rule),
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let highlife = Rule::new(vec![3, 6], vec![2, 3]);
+
+        assert_eq!("B36/S23".parse(), Ok(highlife.clone()));
+        assert_eq!("S23/B36".parse(), Ok(highlife.clone()));
+        assert_eq!("b36s23".parse(), Ok(highlife.clone()));
+        assert_eq!(" 23/36 ".parse(), Ok(highlife));
+        assert_eq!("B2/S".parse(), Ok(Rule::new(vec![2], vec![])));
+        assert_eq!("/2".parse(), Ok(Rule::new(vec![2], vec![])));
+
+        for invalid in [
+            "", "B9/S23", "23/39", "B3/S2/3", "B3/23", "3/S23", "B3/B3", "B3/Sx",
+        ] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_hensel() {
+        let rule = life_like("B2-a/S12".parse().unwrap());
+        assert!(!rule.is_totalistic());
+        // Two adjacent neighbours, then two corners of the same side.
+        assert!(!rule.lives_in(false, 0b0000_0011));
+        assert!(rule.lives_in(false, 0b0000_0101));
+        // Rotated and reflected.
+        assert!(!rule.lives_in(false, 0b1001_0000));
+        assert!(rule.lives_in(false, 0b1010_0000));
+        assert!(rule.lives_in(true, 0b0100_0000));
+
+        assert_eq!("B2-a/S12".parse(), "b2ceikn/s12".parse::<Rule>());
+        assert_eq!("B3/S2-i34q".parse(), "S2ceakn34q/B3".parse::<Rule>());
+        let rule: Rule = "B3ceaiknjqry/S2ceaikn3".parse().unwrap();
+        assert!(life_like(rule.clone()).is_totalistic());
+        assert_eq!(rule, Rule::new(vec![3], vec![2, 3]));
+
+        for invalid in ["B2x/S", "B0c/S", "B2-/S", "B4-b/S", "B1k/S"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_generations() {
+        let brain: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(brain.nb_states(), 3);
+        assert_eq!("/2/3".parse(), Ok(brain.clone()));
+        assert_eq!("b2/s/c3".parse(), Ok(brain.clone()));
+        assert_eq!(brain.to_string(), "B2/S/C3");
+        assert_eq!("345/2/4".parse::<Rule>().unwrap().to_string(), "B2/S345/C4");
+
+        assert_eq!(brain.next_state(0, true), 1);
+        assert_eq!(brain.next_state(1, false), 2);
+        assert_eq!(brain.next_state(2, true), 0);
+        assert_eq!(Rule::new(vec![3], vec![2, 3]).next_state(1, false), 0);
+
+        for invalid in ["B2/S/C1", "B2/S/3", "2/3/C", "B2/S/C256"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_larger_than_life() {
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        let ltl = match bosco {
+            Rule::LargerThanLife(ltl) => ltl,
+            _ => panic!("{} isn't Larger than Life", bosco),
+        };
+        assert_eq!(ltl.radius, 5);
+        assert!(ltl.middle);
+        assert_eq!(ltl.shape, Shape::Moore);
+        assert!(ltl.lives(true, 58) && !ltl.lives(true, 59));
+        assert!(ltl.lives(false, 34) && !ltl.lives(false, 33));
+        assert_eq!(bosco.nb_states(), 2);
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+
+        let rule: Rule = "r2,c3,m0,s2..3,b3..3,nn".parse().unwrap();
+        assert_eq!(rule.nb_states(), 3);
+        assert_eq!(rule.to_string(), "R2,C3,M0,S2..3,B3..3,NN");
+
+        for invalid in [
+            "R0,C0,M1,S1..2,B1..2,NM",
+            "R5,C0,M2,S1..2,B1..2,NM",
+            "R5,C0,M1,S1,B1..2,NM",
+            "R5,C0,M1,S1..2,B1..2,NX",
+            "R5,C0,M1,S1..2,B1..2",
+        ] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_shapes() {
+        let rule: Rule = "B2/S013V".parse().unwrap();
+        assert_eq!(rule.shape(), Shape::VonNeumann);
+        assert_eq!(rule.to_string(), "B2/S013V");
+        let rule = life_like(rule);
+        assert!(!rule.is_totalistic());
+        // North and west, then north and the ignored north-west.
+        assert!(rule.lives_in(false, 0b0000_1010));
+        assert!(!rule.lives_in(false, 0b0000_0011));
+        assert!(rule.lives_in(true, 0b1010_0101));
+
+        let rule: Rule = "34/2h".parse().unwrap();
+        assert_eq!(rule.shape(), Shape::Hexagonal);
+        assert_eq!(rule.to_string(), "B2/S34H");
+        // North-east and south-west are ignored.
+        let rule = life_like(rule);
+        assert!(rule.lives_in(false, 0b0000_0111));
+        assert!(!rule.lives_in(false, 0b0010_0100));
+        assert_eq!("B2/S/C3H".parse::<Rule>().unwrap().to_string(), "B2/S/C3H");
+
+        for invalid in ["B5/SV", "B2/S7H", "B2c/SV", "B2/S1V2"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_b0() {
+        let life = Rule::new(vec![3], vec![2, 3]);
+        assert_eq!(life.at_generation(1), life);
+        assert!(!life.is_inverted(1));
+
+        // Without S8, the background alternates.
+        let rule: Rule = "B0/S2".parse().unwrap();
+        let (even, odd) = (rule.at_generation(0), rule.at_generation(1));
+        assert!(!rule.is_inverted(0) && rule.is_inverted(1) && !rule.is_inverted(2));
+        assert_eq!(rule.at_generation(2), even);
+        let (even, odd) = (life_like(even), life_like(odd));
+        assert!(!even.lives_in(false, 0) && !odd.lives_in(false, 0));
+        // A live cell with 2 neighbours survives, and is stored dead at odd generations.
+        assert!(!even.lives_in(true, 0b11));
+        // A stored dead cell with 6 stored dead neighbours is a live cell with 2 live neighbours.
+        assert!(odd.lives_in(false, 0b1111_1100));
+
+        // With S8, the background stays alive.
+        let rule: Rule = "B0/S8".parse().unwrap();
+        assert!(rule.is_inverted(1) && rule.is_inverted(2));
+        assert_eq!(rule.at_generation(1), rule.at_generation(2));
+        assert!(!life_like(rule.at_generation(1)).lives_in(false, 0));
+
+        assert!("B0/S/C3".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_map() {
+        let life = "MAPARYXfhZofugWaH7oaIDogBZofuhogOiAaIDogIAAgAAWaH7oaIDogGiA6ICAAIAAaIDogIAAgACAAIAAAAAAAA";
+        assert_eq!(life.parse(), Ok(Rule::new(vec![3], vec![2, 3])));
+        assert_eq!(
+            format!("{}==", life).parse(),
+            Ok(Rule::new(vec![3], vec![2, 3]))
+        );
+
+        // Cells take the state of their north neighbour.
+        let north = "MAPAAAAAAAAAAAAAAAAAAAAAP////////////////////8AAAAAAAAAAAAAAAAAAAAA/////////////////////w";
+        let rule: Rule = north.parse().unwrap();
+        assert_eq!(rule.to_string(), north);
+        let rule = life_like(rule);
+        assert!(rule.lives_in(false, 0b0000_0010));
+        assert!(!rule.lives_in(true, 0b0100_0000));
+
+        for invalid in [
+            "MAP",
+            "MAPARYX",
+            &life[..life.len() - 1],
+            &format!("{}A", life),
+        ] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_named_rules() {
+        assert_eq!("Life".parse(), Ok(Rule::new(vec![3], vec![2, 3])));
+        assert_eq!("highlife".parse(), Ok(Rule::new(vec![3, 6], vec![2, 3])));
+        assert_eq!(
+            " Day & Night ".parse(),
+            Ok(Rule::new(vec![3, 6, 7, 8], vec![3, 4, 6, 7, 8]))
+        );
+        assert!("Lif".parse::<Rule>().is_err());
+
+        let ant: Rule = "Langton's Ant".parse().unwrap();
+        assert_eq!(ant, Rule::Turmite(Arc::new("RL".parse().unwrap())));
+        assert_eq!(ant.nb_states(), 2);
+        assert_eq!("LLRR".parse::<Rule>().map(|rule| rule.nb_states()), Ok(4));
+
+        for (name, rule) in NAMED_RULES.iter() {
+            let rule: Rule = rule.parse().unwrap();
+            assert_eq!(rule.name(), Some(*name));
+        }
+        assert_eq!(Rule::new(vec![3], vec![2]).name(), None);
+    }
+
+    #[test]
+    fn test_margolus() {
+        let bbm = "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15";
+        let rule: Rule = bbm.parse().unwrap();
+        assert_eq!(rule.to_string(), bbm);
+        assert_eq!(rule.name(), Some("BBM"));
+        assert!(!rule.is_inverted(1));
+
+        // A lone ball goes to the opposite corner of its block.
+        let rc = |row, col| RowCol { row, col };
+        let (even, odd) = match (rule.at_generation(0), rule.at_generation(1)) {
+            (Rule::Margolus(even), Rule::Margolus(odd)) => (even, odd),
+            _ => panic!("{} isn't a block rule", rule),
+        };
+        assert!(even.lives(rc(1, 1), false, 0b0000_0001));
+        assert!(!even.lives(rc(0, 0), true, 0));
+        assert!(!even.lives(rc(2, 2), false, 0b0000_0001));
+        assert!(odd.lives(rc(2, 2), false, 0b0000_0001));
+
+        // Tron flips uniform blocks, and therefore the background.
+        let tron: Rule = "Tron".parse().unwrap();
+        assert!(tron.is_inverted(1) && !tron.is_inverted(2));
+
+        for invalid in [
+            "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14",
+            "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;16",
+            "MS,D3;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15",
+        ] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_wolfram() {
+        let rule: Rule = "W30".parse().unwrap();
+        assert_eq!(rule.to_string(), "W30");
+
+        // Rule 30 is 00011110 in binary.
+        let (wolfram, later) = match (&rule, rule.at_generation(9)) {
+            (Rule::Wolfram(wolfram), Rule::Wolfram(later)) => (wolfram, later),
+            _ => panic!("{} isn't one-dimensional", rule),
+        };
+        assert!(wolfram.lives([false, false, true]));
+        assert!(wolfram.lives([true, false, false]));
+        assert!(!wolfram.lives([true, true, false]));
+        assert!(!wolfram.lives([false, false, false]));
+        assert_eq!(wolfram.row(), 1);
+        assert_eq!(later.row(), 10);
+
+        for invalid in ["W", "W256", "W-1", "W3x"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_lenia() {
+        let orbium = "Lenia(R13,T10,m0.15,s0.015)";
+        let rule: Rule = orbium.parse().unwrap();
+        assert_eq!(rule.to_string(), orbium);
+        assert_eq!(rule.nb_states(), 255);
+
+        let lenia = match rule {
+            Rule::Lenia(lenia) => lenia,
+            _ => panic!("{} isn't a Lenia rule", rule),
+        };
+        assert_eq!(lenia.radius, 13);
+        assert_eq!(lenia.growth(0.15), 1.0);
+        assert!(lenia.growth(0.5) + 1.0 < 1e-6);
+        assert_eq!(lenia.kernel(6.5), 1.0);
+        assert_eq!(lenia.kernel(13.0), 0.0);
+
+        for invalid in [
+            "Lenia(R13,T10,m0.15)",
+            "Lenia(R0,T10,m0.15,s0.015)",
+            "Lenia(R13,T10,m0.15,s0)",
+            "Lenia(R13,T10,mNaN,s0.015)",
+            "Lenia(R13,T10,s0.015,m0.15)",
+        ] {
+            assert!(invalid.parse::<Rule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for rule in [
+            "B3/S23",
+            "B36/S23",
+            "B/S012345678",
+            "B0123478/S01234678",
+            "B2/S",
+        ] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+        assert_eq!("s32b63".parse::<Rule>().unwrap().to_string(), "B36/S23");
+
+        for rule in [
+            "B2-a/S12",
+            "B3/S2-i34q",
+            "B3-cnqy/S23-a4iyt",
+            "B3-cnqy/S23-a4ity",
+            "B5e6-k7c/S0",
+        ] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+        // Letters are printed in the order they were parsed, but don't change the rule.
+        assert_eq!(
+            "B3-yqnc/S23-a4ity".parse::<Rule>(),
+            "B3-cnqy/S23-a4iyt".parse::<Rule>()
+        );
+        assert_eq!("B2iei/S".parse::<Rule>().unwrap().to_string(), "B2ie/S");
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
@@ -0,0 +1,635 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read};
+use std::path::Path;
+
+use super::rule::Shape;
+
+/* --------------------------------------------------------------------------------------------- */
+
+// Inputs of a rule: the cell, then its neighbours following `NEIGHBOURS`.
+const CELL: usize = 0;
+const NW: usize = 1;
+const N: usize = 2;
+const NE: usize = 3;
+const W: usize = 4;
+const E: usize = 5;
+const SW: usize = 6;
+const S: usize = 7;
+const SE: usize = 8;
+
+// Neighbours in @TABLE transitions, clockwise from the north.
+fn table_neighbours(shape: Shape) -> &'static [usize] {
+    match shape {
+        Shape::Moore => &[N, NE, E, SE, S, SW, W, NW],
+        Shape::VonNeumann => &[N, E, S, W],
+        Shape::Hexagonal => &[N, E, SE, S, W, NW],
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, reason.into())
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+// Permutations of the neighbours of a transition which are equivalent to it.
+enum Symmetry {
+    Permutations(Vec<Vec<usize>>),
+    // Any permutation.
+    Permute,
+}
+
+impl Symmetry {
+    // Golly's `none`, `reflect_horizontal`, `rotate4`, `rotate8reflect`, `permute`... for
+    // `nb_neighbours` neighbours.
+    fn new(name: &str, nb_neighbours: usize) -> Option<Self> {
+        let (nb_rotations, reflect) = match name {
+            "permute" => return Some(Symmetry::Permute),
+            "none" => (1, false),
+            "reflect_horizontal" => (1, true),
+            _ => {
+                let (name, reflect) = match name.strip_suffix("reflect") {
+                    Some(name) => (name, true),
+                    None => (name, false),
+                };
+                (name.strip_prefix("rotate")?.parse::<usize>().ok()?, reflect)
+            }
+        };
+        if nb_rotations == 0 || !nb_neighbours.is_multiple_of(nb_rotations) {
+            return None;
+        }
+
+        let n = nb_neighbours;
+        let mut permutations = vec![];
+        for rotation in (0..n).step_by(n / nb_rotations) {
+            permutations.push((0..n).map(|i| (i + rotation) % n).collect());
+            if reflect {
+                permutations.push((0..n).map(|i| (n - i + rotation) % n).collect());
+            }
+        }
+
+        Some(Symmetry::Permutations(permutations))
+    }
+
+    // Distinct transitions equivalent to one with inputs `cell` and `neighbours`.
+    fn apply(&self, cell: &[u8], neighbours: &[Vec<u8>]) -> Vec<Vec<Vec<u8>>> {
+        let mut images: Vec<Vec<Vec<u8>>> = vec![];
+
+        match self {
+            Symmetry::Permutations(permutations) => {
+                for permutation in permutations {
+                    let image: Vec<_> = std::iter::once(cell.to_vec())
+                        .chain(permutation.iter().map(|&i| neighbours[i].clone()))
+                        .collect();
+                    if !images.contains(&image) {
+                        images.push(image);
+                    }
+                }
+            }
+            Symmetry::Permute => {
+                let mut image = vec![cell.to_vec()];
+                Self::distinct_permutations(
+                    neighbours,
+                    &mut vec![false; neighbours.len()],
+                    &mut image,
+                    &mut images,
+                );
+            }
+        }
+
+        images
+    }
+
+    // Appends to `images` the distinct ways of completing `image` with the unused `items`.
+    fn distinct_permutations(
+        items: &[Vec<u8>],
+        used: &mut Vec<bool>,
+        image: &mut Vec<Vec<u8>>,
+        images: &mut Vec<Vec<Vec<u8>>>,
+    ) {
+        if used.iter().all(|&u| u) {
+            images.push(image.clone());
+            return;
+        }
+
+        for i in 0..items.len() {
+            // Equal items are only tried once at each position.
+            if used[i] || (0..i).any(|j| !used[j] && items[j] == items[i]) {
+                continue;
+            }
+            used[i] = true;
+            image.push(items[i].clone());
+            Self::distinct_permutations(items, used, image, images);
+            image.pop();
+            used[i] = false;
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+// States accepted by each input of a transition, the cell then its neighbours in the order of
+// `table_neighbours`, and the next state.
+type Transition = (Vec<Vec<u8>>, u8);
+
+// An element of a transition: states, or a variable which is bound if it appears several times.
+enum Element {
+    States(Vec<u8>),
+    Variable(String),
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+#[derive(Debug, Eq, PartialEq)]
+enum Kind {
+    // For each input which matters and each of its states, the bit set of the transitions which
+    // accept it there. The first transition accepting all inputs gives the next state, which is
+    // the current one if there is none.
+    Table {
+        inputs: Vec<usize>,
+        accepts: Vec<Vec<Vec<u64>>>,
+        outputs: Vec<u8>,
+    },
+    // Nodes of the tree, the root last, which look at `inputs` in order. Children of the nodes
+    // of the last level are next states.
+    Tree {
+        inputs: Vec<usize>,
+        nodes: Vec<Vec<u32>>,
+    },
+}
+
+/// A rule of Golly's `.rule` files, such as Wireworld or Langton's loops: the next state of a
+/// cell is given by a transition table (`@TABLE`) or a decision tree (`@TREE`) over its state and
+/// the states of its neighbours.
+#[derive(Debug, Eq, PartialEq)]
+pub struct RuleTable {
+    pub name: String,
+    pub nb_states: u8,
+    pub shape: Shape,
+    kind: Kind,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl RuleTable {
+    /// Reads `name.rule` from the current directory, or else from `rules/`.
+    pub fn load(name: &str) -> io::Result<Self> {
+        let file_name = format!("{}.rule", name);
+        let file =
+            File::open(&file_name).or_else(|_| File::open(Path::new("rules").join(&file_name)))?;
+
+        Self::read(BufReader::new(file))
+    }
+
+    /// Reads the `@RULE` name and the `@TABLE`, or else the `@TREE`, of a `.rule` file. Other
+    /// sections are ignored.
+    pub fn read<R: Read>(reader: BufReader<R>) -> io::Result<Self> {
+        let mut name = None;
+        let mut section = None;
+        let mut sections: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+
+        for (nb, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            if let Some(header) = line.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = words.next().map(str::to_string);
+                if section.as_deref() == Some("RULE") {
+                    name = words.next().map(str::to_string);
+                }
+                continue;
+            }
+
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let (Some(section), false) = (&section, line.is_empty()) {
+                sections
+                    .entry(section.clone())
+                    .or_default()
+                    .push((nb + 1, line.to_string()));
+            }
+        }
+
+        let name = name.ok_or_else(|| invalid("expected @RULE followed by a name"))?;
+        if let Some(lines) = sections.get("TABLE") {
+            Self::read_table(name, lines)
+        } else if let Some(lines) = sections.get("TREE") {
+            Self::read_tree(name, lines)
+        } else {
+            Err(invalid("expected @TABLE or @TREE"))
+        }
+    }
+
+    /// The next state of a cell, given its state and the states of its neighbours following
+    /// `NEIGHBOURS`. States the rule doesn't have are taken as 0.
+    pub fn next_state(&self, state: u8, neighbours: &[u8; 8]) -> u8 {
+        let input = |i: usize| {
+            let state = if i == CELL { state } else { neighbours[i - 1] };
+            if state < self.nb_states {
+                state as usize
+            } else {
+                0
+            }
+        };
+
+        match &self.kind {
+            Kind::Table {
+                inputs,
+                accepts,
+                outputs,
+            } => (0..outputs.len().div_ceil(64))
+                .find_map(|word| {
+                    let matches = inputs
+                        .iter()
+                        .zip(accepts)
+                        .fold(!0u64, |acc, (&i, accepts)| acc & accepts[input(i)][word]);
+                    (matches != 0).then(|| outputs[word * 64 + matches.trailing_zeros() as usize])
+                })
+                .unwrap_or(state),
+            Kind::Tree { inputs, nodes } => inputs
+                .iter()
+                .fold(nodes.len() - 1, |node, &i| nodes[node][input(i)] as usize)
+                as u8,
+        }
+    }
+
+    fn read_table(name: String, lines: &[(usize, String)]) -> io::Result<Self> {
+        let mut nb_states = None;
+        let mut shape = Shape::Moore;
+        let mut symmetry = "none".to_string();
+        let mut variables = HashMap::new();
+        // With variables and symmetries expanded.
+        let mut transitions: Vec<Transition> = vec![];
+
+        for (nb, line) in lines {
+            let error = |reason: &str| invalid(format!("line {}: {}", nb, reason));
+
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                match key.trim() {
+                    "n_states" => match value.parse::<u8>() {
+                        Ok(n) if n >= 2 => nb_states = Some(n),
+                        _ => return Err(error("expected a number of states from 2 to 255")),
+                    },
+                    "neighborhood" => {
+                        shape = match value {
+                            "Moore" => Shape::Moore,
+                            "vonNeumann" => Shape::VonNeumann,
+                            "hexagonal" => Shape::Hexagonal,
+                            _ => return Err(error("unsupported neighborhood")),
+                        }
+                    }
+                    "symmetries" => symmetry = value.to_string(),
+                    _ => return Err(error("unknown setting")),
+                }
+                continue;
+            }
+
+            let nb_states = nb_states.ok_or_else(|| error("expected n_states first"))?;
+            let element = |token: &str| Self::read_element(token.trim(), nb_states, &variables);
+
+            if let Some(variable) = line.strip_prefix("var ") {
+                let (variable, states) = variable
+                    .split_once('=')
+                    .ok_or_else(|| error("expected '='"))?;
+                let states = match element(states).map_err(error)? {
+                    Element::States(states) => states,
+                    Element::Variable(name) => variables[&name].clone(),
+                };
+                variables.insert(variable.trim().to_string(), states);
+                continue;
+            }
+
+            let neighbours = table_neighbours(shape).len();
+            let symmetry = Symmetry::new(&symmetry, neighbours)
+                .ok_or_else(|| error("unsupported symmetries"))?;
+
+            // Without commas, each character is a state or a variable.
+            let tokens: Vec<String> = if line.contains([',', '{']) {
+                Self::split_transition(line)
+            } else {
+                line.chars().map(String::from).collect()
+            };
+            if tokens.len() != neighbours + 2 {
+                return Err(error("wrong number of states"));
+            }
+            let elements = tokens
+                .iter()
+                .map(|t| element(t))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?;
+
+            for (inputs, output) in Self::bind(&elements, &variables).map_err(error)? {
+                for inputs in symmetry.apply(&inputs[0], &inputs[1..]) {
+                    transitions.push((inputs, output));
+                }
+            }
+        }
+
+        let nb_states = nb_states.ok_or_else(|| invalid("expected n_states"))?;
+        let inputs: Vec<_> = std::iter::once(CELL)
+            .chain(table_neighbours(shape).iter().copied())
+            .collect();
+        let nb_words = transitions.len().div_ceil(64);
+        let mut accepts = vec![vec![vec![0; nb_words]; nb_states as usize]; inputs.len()];
+
+        for (t, (states, _)) in transitions.iter().enumerate() {
+            for (accepts, states) in accepts.iter_mut().zip(states) {
+                for &state in states {
+                    accepts[state as usize][t / 64] |= 1 << (t % 64);
+                }
+            }
+        }
+
+        Ok(RuleTable {
+            name,
+            nb_states,
+            shape,
+            kind: Kind::Table {
+                inputs,
+                accepts,
+                outputs: transitions.into_iter().map(|(_, output)| output).collect(),
+            },
+        })
+    }
+
+    // Splits a transition such as `0,a,{1,2},...` at commas outside braces.
+    fn split_transition(line: &str) -> Vec<String> {
+        let mut tokens = vec![String::new()];
+        let mut depth = 0;
+
+        for c in line.chars() {
+            match c {
+                ',' if depth == 0 => tokens.push(String::new()),
+                _ => {
+                    depth += (c == '{') as i32 - (c == '}') as i32;
+                    tokens.last_mut().unwrap().push(c);
+                }
+            }
+        }
+
+        tokens
+    }
+
+    // A state, a variable, or states between braces, possibly given by variables.
+    fn read_element(
+        token: &str,
+        nb_states: u8,
+        variables: &HashMap<String, Vec<u8>>,
+    ) -> Result<Element, &'static str> {
+        let state = |token: &str| match token.parse::<u8>() {
+            Ok(state) if state < nb_states => Ok(state),
+            Ok(_) => Err("state out of range"),
+            Err(_) => Err("unknown variable"),
+        };
+
+        if let Some(states) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            let mut all = vec![];
+            for token in states.split(',').map(str::trim) {
+                match variables.get(token) {
+                    Some(states) => all.extend(states),
+                    None => all.push(state(token)?),
+                }
+            }
+            Ok(Element::States(all))
+        } else if variables.contains_key(token) {
+            Ok(Element::Variable(token.to_string()))
+        } else {
+            Ok(Element::States(vec![state(token)?]))
+        }
+    }
+
+    // Transitions with the states of inputs and the output, one for each value of variables
+    // which appear several times or in the output, which are bound to the same state everywhere.
+    fn bind(
+        elements: &[Element],
+        variables: &HashMap<String, Vec<u8>>,
+    ) -> Result<Vec<Transition>, &'static str> {
+        let (output, inputs) = elements.split_last().unwrap();
+
+        let mut bound: Vec<&str> = vec![];
+        for element in elements {
+            if let Element::Variable(name) = element {
+                let appearances = inputs
+                    .iter()
+                    .filter(|e| matches!(e, Element::Variable(n) if n == name))
+                    .count();
+                if appearances == 0 {
+                    return Err("variable of the next state not in the inputs");
+                }
+                let is_output = std::ptr::eq(element, output);
+                if (appearances > 1 || is_output) && !bound.contains(&name.as_str()) {
+                    bound.push(name);
+                }
+            }
+        }
+
+        let mut bindings = vec![HashMap::new()];
+        for &name in &bound {
+            bindings = bindings
+                .into_iter()
+                .flat_map(|binding| {
+                    variables[name].iter().map(move |&state| {
+                        let mut binding = binding.clone();
+                        binding.insert(name, state);
+                        binding
+                    })
+                })
+                .collect();
+        }
+
+        bindings
+            .into_iter()
+            .map(|binding| {
+                let states = |element: &Element| match element {
+                    Element::States(states) => states.clone(),
+                    Element::Variable(name) => match binding.get(name.as_str()) {
+                        Some(&state) => vec![state],
+                        None => variables[name].clone(),
+                    },
+                };
+                let output = match states(output)[..] {
+                    [state] => state,
+                    _ => return Err("expected a single next state"),
+                };
+
+                Ok((inputs.iter().map(states).collect(), output))
+            })
+            .collect()
+    }
+
+    fn read_tree(name: String, lines: &[(usize, String)]) -> io::Result<Self> {
+        let mut settings = HashMap::new();
+        let mut nodes: Vec<(usize, Vec<u32>)> = vec![];
+
+        for (nb, line) in lines {
+            if let Some((key, value)) = line.split_once('=') {
+                settings.insert(key.trim(), value.trim());
+                continue;
+            }
+
+            let numbers = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(format!("line {}: expected numbers", nb)))?;
+            nodes.push((*nb, numbers));
+        }
+
+        let setting = |key: &str| -> io::Result<u32> {
+            settings
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| invalid(format!("expected {}", key)))
+        };
+        let nb_states = match setting("num_states")? {
+            n @ 2..=255 => n,
+            _ => return Err(invalid("expected from 2 to 255 states")),
+        };
+        let (shape, inputs) = match setting("num_neighbors")? {
+            4 => (Shape::VonNeumann, vec![N, W, E, S, CELL]),
+            8 => (Shape::Moore, vec![NW, NE, SW, SE, N, W, E, S, CELL]),
+            _ => return Err(invalid("expected 4 or 8 neighbors")),
+        };
+        if setting("num_nodes")? as usize != nodes.len() || nodes.is_empty() {
+            return Err(invalid("wrong number of nodes"));
+        }
+
+        // Children of nodes of level 1 are states, children of other nodes are previous nodes
+        // of the level below.
+        for (i, (nb, node)) in nodes.iter().enumerate() {
+            let valid = match node.split_first() {
+                Some((&1, children)) => children.iter().all(|&c| c < nb_states),
+                Some((&level, children)) => children.iter().all(|&c| {
+                    (c as usize) < i && nodes[c as usize].1.first() == Some(&(level - 1))
+                }),
+                None => false,
+            };
+            if !valid || node.len() != nb_states as usize + 1 {
+                return Err(invalid(format!("line {}: invalid node", nb)));
+            }
+        }
+        if nodes.last().unwrap().1[0] as usize != inputs.len() {
+            return Err(invalid("the last node must be the root"));
+        }
+
+        Ok(RuleTable {
+            name,
+            nb_states: nb_states as u8,
+            shape,
+            kind: Kind::Tree {
+                inputs,
+                nodes: nodes
+                    .into_iter()
+                    .map(|(_, node)| node[1..].to_vec())
+                    .collect(),
+            },
+        })
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn read(rule: &str) -> io::Result<RuleTable> {
+        RuleTable::read(BufReader::new(rule.as_bytes()))
+    }
+
+    const WIREWORLD: &str = "@RULE WireWorld
+# Empty, electron head, electron tail and conductor.
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,1,h,i,j,k,l,m,1
+3,1,h,i,j,k,l,m,{0,2,3},1
+@COLORS
+1 0 128 255
+";
+
+    #[test]
+    fn test_table() {
+        let wireworld = read(WIREWORLD).unwrap();
+        assert_eq!(wireworld.name, "WireWorld");
+        assert_eq!(wireworld.nb_states, 4);
+
+        assert_eq!(wireworld.next_state(1, &[3, 3, 0, 0, 2, 0, 0, 0]), 2);
+        assert_eq!(wireworld.next_state(2, &[0; 8]), 3);
+        assert_eq!(wireworld.next_state(0, &[1, 1, 1, 0, 0, 0, 0, 0]), 0);
+        // A conductor becomes an electron head next to one or two of them, in any position.
+        assert_eq!(wireworld.next_state(3, &[0, 0, 0, 0, 0, 0, 0, 1]), 1);
+        assert_eq!(wireworld.next_state(3, &[0, 1, 0, 2, 0, 0, 1, 0]), 1);
+        assert_eq!(wireworld.next_state(3, &[1, 0, 1, 0, 0, 1, 0, 0]), 3);
+
+        // Bound variables, rotations, and the compact form.
+        let rule = read(
+            "@RULE Test\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:rotate4\n\
+             var a={1,2}\n0,a,0,a,0,a\n010002\n",
+        )
+        .unwrap();
+        // North and south, then east and west.
+        assert_eq!(rule.next_state(0, &[0, 2, 0, 0, 0, 0, 2, 0]), 2);
+        assert_eq!(rule.next_state(0, &[0, 0, 0, 1, 1, 0, 0, 0]), 1);
+        assert_eq!(rule.next_state(0, &[0, 1, 0, 0, 0, 0, 2, 0]), 0);
+        // A single neighbour, then one which isn't a von Neumann neighbour.
+        assert_eq!(rule.next_state(0, &[0, 0, 0, 0, 1, 0, 0, 0]), 2);
+        assert_eq!(rule.next_state(0, &[0, 0, 1, 0, 0, 0, 0, 0]), 0);
+
+        for invalid in [
+            "@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,0,1\n",
+            "@RULE X\n@TABLE\n0,0,0,0,0,0,0,0,0,1\n",
+            "@RULE X\n@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,1\n",
+            "@RULE X\n@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,2,1\n",
+            "@RULE X\n@TABLE\nn_states:2\nvar a={0,1}\n0,0,0,0,0,0,0,0,0,b\n",
+            "@RULE X\n@TABLE\nn_states:2\nsymmetries:rotate3\n0,0,0,0,0,0,0,0,0,1\n",
+        ] {
+            assert!(read(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        // A cell takes the state of its north neighbour, among 2 states with von Neumann
+        // neighbours, looked at in the order n, w, e, s, c.
+        let rule = "@RULE North\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=9\n\
+                    1 0 0\n1 1 1\n2 0 0\n2 1 1\n3 2 2\n3 3 3\n4 4 4\n4 5 5\n5 6 7\n";
+        let north = read(rule).unwrap();
+        assert_eq!(north.shape, Shape::VonNeumann);
+        assert_eq!(north.next_state(0, &[0, 1, 0, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(north.next_state(1, &[1, 0, 1, 1, 1, 1, 1, 1]), 0);
+
+        for invalid in [
+            // The root isn't of level 5.
+            "@RULE X\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=2\n1 0 0\n2 0 0\n",
+            "@RULE X\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=1\n1 0 2\n",
+            "@RULE X\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=2\n1 0 0\n3 0 0\n",
+            "@RULE X\n@TREE\nnum_states=2\nnum_neighbors=6\nnum_nodes=1\n1 0 0\n",
+        ] {
+            assert!(read(invalid).is_err(), "{}", invalid);
+        }
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
@@ -0,0 +1,227 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::grid::{Grid, RowCol, NEIGHBOURS};
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Only stores live cells, so a pattern is not limited by the number of rows and columns, which
+/// merely describe the visible part of the universe.
+#[derive(Clone, Debug)]
+pub struct SparseGrid {
+    cells: HashSet<RowCol>,
+    // States of dying cells.
+    dying: HashMap<RowCol, u8>,
+    nb_rows: usize,
+    nb_cols: usize,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl SparseGrid {
+    fn neighbours(rc: RowCol) -> impl Iterator<Item = RowCol> {
+        NEIGHBOURS.iter().map(move |&(dr, dc)| RowCol {
+            row: rc.row + dr,
+            col: rc.col + dc,
+        })
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Grid for SparseGrid {
+    fn new(nb_rows: usize, nb_cols: usize) -> Self {
+        SparseGrid {
+            cells: HashSet::new(),
+            dying: HashMap::new(),
+            nb_rows,
+            nb_cols,
+        }
+    }
+
+    fn at(&self, rc: RowCol) -> bool {
+        self.cells.contains(&rc)
+    }
+
+    fn set(&mut self, rc: RowCol, value: bool) {
+        self.set_state(rc, value as u8);
+    }
+
+    fn state(&self, rc: RowCol) -> u8 {
+        if self.cells.contains(&rc) {
+            1
+        } else {
+            self.dying.get(&rc).copied().unwrap_or(0)
+        }
+    }
+
+    fn set_state(&mut self, rc: RowCol, state: u8) {
+        self.cells.remove(&rc);
+        self.dying.remove(&rc);
+
+        match state {
+            0 => {}
+            1 => {
+                self.cells.insert(rc);
+            }
+            _ => {
+                self.dying.insert(rc, state);
+            }
+        }
+    }
+
+    fn count_live_neighbours(&self, rc: RowCol) -> u8 {
+        Self::neighbours(rc)
+            .filter(|n| self.cells.contains(n))
+            .count() as u8
+    }
+
+    fn count_live_cells(&self) -> u64 {
+        self.cells.len() as u64
+    }
+
+    fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    fn nb_columns(&self) -> usize {
+        self.nb_cols
+    }
+
+    fn live_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        Box::new(self.cells.iter().copied())
+    }
+
+    fn cell_states(&self) -> Box<dyn Iterator<Item = (RowCol, u8)> + '_> {
+        let dying = self.dying.iter().map(|(&rc, &state)| (rc, state));
+
+        Box::new(self.cells.iter().map(|&rc| (rc, 1)).chain(dying))
+    }
+
+    fn clear_like(&mut self, other: &Self) {
+        self.cells.clear();
+        self.dying.clear();
+        self.nb_rows = other.nb_rows;
+        self.nb_cols = other.nb_cols;
+    }
+
+    fn active_area(&self) -> (Range<i64>, Range<i64>) {
+        let bounds = self.cells.iter().chain(self.dying.keys()).fold(
+            None,
+            |bounds: Option<(Range<i64>, Range<i64>)>, rc| {
+                Some(match bounds {
+                    None => (rc.row - 1..rc.row + 2, rc.col - 1..rc.col + 2),
+                    Some((rows, cols)) => (
+                        i64::min(rows.start, rc.row - 1)..i64::max(rows.end, rc.row + 2),
+                        i64::min(cols.start, rc.col - 1)..i64::max(cols.end, rc.col + 2),
+                    ),
+                })
+            },
+        );
+
+        bounds.unwrap_or((0..0, 0..0))
+    }
+
+    fn active_cells(&self) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        self.active_cells_in(i64::MIN..i64::MAX)
+    }
+
+    fn active_cells_in(&self, rows: Range<i64>) -> Box<dyn Iterator<Item = RowCol> + '_> {
+        // Cells in other states than live ones may have an effect on their neighbours with the
+        // rules of `.rule` files.
+        let active: HashSet<_> = self
+            .cells
+            .iter()
+            .chain(self.dying.keys())
+            .flat_map(|&rc| std::iter::once(rc).chain(Self::neighbours(rc)))
+            .filter(|rc| rows.contains(&rc.row))
+            .collect();
+
+        Box::new(active.into_iter())
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::glider::rle::{Rle, RleEntry};
+
+    #[test]
+    fn test_count_live_neighbours() {
+        let mut g = SparseGrid::new(3, 3);
+        g.set(RowCol { row: 0, col: 0 }, true);
+        g.set(RowCol { row: 0, col: 2 }, true);
+        g.set(RowCol { row: 1, col: 1 }, true);
+
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 0 }), 1);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 1 }), 3);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 1, col: 1 }), 2);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 2, col: 2 }), 1);
+        assert_eq!(g.count_live_cells(), 3);
+
+        g.set(RowCol { row: 1, col: 1 }, false);
+        assert_eq!(g.count_live_neighbours(RowCol { row: 0, col: 1 }), 2);
+        assert_eq!(g.count_live_cells(), 2);
+    }
+
+    #[test]
+    fn test_active_cells() {
+        let mut g = SparseGrid::new(1000, 1000);
+        g.set(RowCol { row: 0, col: 0 }, true);
+        g.set(RowCol { row: 10, col: 10 }, true);
+
+        let mut active: Vec<_> = g.active_cells().collect();
+        active.sort_by_key(|rc| (rc.row, rc.col));
+
+        assert_eq!(active.len(), 9 + 9);
+        assert_eq!(active[0], RowCol { row: -1, col: -1 });
+    }
+
+    #[test]
+    fn test_beyond_bounds() {
+        let mut g = SparseGrid::new(2, 2);
+        g.set(
+            RowCol {
+                row: 5000,
+                col: 5000,
+            },
+            true,
+        );
+
+        assert!(g.at(RowCol {
+            row: 5000,
+            col: 5000
+        }));
+        assert_eq!(g.count_live_cells(), 1);
+    }
+
+    #[test]
+    fn test_new_from_rle_position() {
+        // 2o$bo!
+        let rle = Rle {
+            pattern: vec![
+                RleEntry::Live(2),
+                RleEntry::NewRow(1),
+                RleEntry::Dead(1),
+                RleEntry::Live(1),
+            ],
+            position: Some(RowCol { row: -8, col: -6 }),
+        };
+
+        // The position is relative to the centre of the grid.
+        let g = SparseGrid::new_from_rle(&rle, 10, 10);
+
+        assert_eq!(g.count_live_cells(), 3);
+        assert!(g.at(RowCol { row: -3, col: -1 }));
+        assert!(g.at(RowCol { row: -3, col: 0 }));
+        assert!(g.at(RowCol { row: -2, col: 0 }));
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
@@ -0,0 +1,268 @@
+/* --------------------------------------------------------------------------------------------- */
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::grid::RowCol;
+
+/* --------------------------------------------------------------------------------------------- */
+
+// Turns of Golly's turmite rules, as quarter turns to the right.
+const TURNS: [(u8, char, u8); 4] = [(1, 'N', 0), (2, 'R', 1), (4, 'U', 2), (8, 'L', 3)];
+
+// Rows and columns of a step towards the north, east, south and west.
+const HEADINGS: [(i64, i64); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// An ant walking on the grid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ant {
+    pub position: RowCol,
+    /// North (0), east (1), south (2) or west (3).
+    pub heading: u8,
+    pub state: u8,
+}
+
+impl Ant {
+    /// An ant in state 0 heading north.
+    pub fn new(position: RowCol) -> Self {
+        Ant {
+            position,
+            heading: 0,
+            state: 0,
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// A turmite rule, in Golly's `{{{1,2,0},{0,8,0}}}` notation: for each state of the ant and each
+/// colour of the cell it stands on, the colour it writes there, how it turns (1 for no turn, 2
+/// right, 4 back and 8 left) and its next state, after which it moves one cell forward. Ants
+/// with a single state can also be written with one turn per colour, as in `RL` for Langton's
+/// ant, each colour becoming the next one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Turmite {
+    // Colour written, quarter turns to the right and next state.
+    transitions: Vec<Vec<(u8, u8, u8)>>,
+}
+
+impl Turmite {
+    pub fn nb_colours(&self) -> u8 {
+        self.transitions[0].len() as u8
+    }
+
+    /// The colour written by an ant on a cell of `colour`, and the ant once it moved. Cells of
+    /// unknown colours, as RLE files may give, are of colour 0.
+    pub fn next(&self, ant: &Ant, colour: u8) -> (u8, Ant) {
+        let colour = if colour < self.nb_colours() {
+            colour
+        } else {
+            0
+        };
+        let (write, turn, state) = self.transitions[ant.state as usize][colour as usize];
+        let heading = (ant.heading + turn) % 4;
+        let (dr, dc) = HEADINGS[heading as usize];
+
+        let ant = Ant {
+            position: RowCol {
+                row: ant.position.row + dr,
+                col: ant.position.col + dc,
+            },
+            heading,
+            state,
+        };
+
+        (write, ant)
+    }
+
+    // Whether the ant has one state, and each colour becomes the next one.
+    fn is_ant(&self) -> bool {
+        let nb_colours = self.nb_colours();
+
+        self.transitions.len() == 1
+            && self.transitions[0]
+                .iter()
+                .enumerate()
+                .all(|(colour, &(write, _, _))| write == (colour as u8 + 1) % nb_colours)
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl FromStr for Turmite {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let transitions = if spec.starts_with('{') {
+            // Values of each transition, of each state.
+            let mut transitions = vec![];
+            let mut depth = 0;
+            let mut values = vec![];
+            let mut value = String::new();
+
+            for c in spec.chars() {
+                match c {
+                    '{' if depth < 3 => {
+                        depth += 1;
+                        if depth == 2 {
+                            transitions.push(vec![]);
+                        }
+                    }
+                    '0'..='9' if depth == 3 => value.push(c),
+                    ',' | '}' if depth == 3 => {
+                        values.push(value.parse::<u8>().map_err(|_| "expected a number")?);
+                        value.clear();
+                        if c == '}' {
+                            let transition = match values[..] {
+                                [write, turn, state] => (write, turn, state),
+                                _ => return Err("expected 3 numbers per transition"),
+                            };
+                            transitions.last_mut().unwrap().push(transition);
+                            values.clear();
+                            depth -= 1;
+                        }
+                    }
+                    '}' if depth > 0 => depth -= 1,
+                    ',' if depth > 0 => {}
+                    _ => return Err("unexpected character"),
+                }
+            }
+            if depth != 0 {
+                return Err("unbalanced braces");
+            }
+
+            for (_, turn, _) in transitions.iter_mut().flatten() {
+                *turn = TURNS
+                    .iter()
+                    .find(|&&(golly, _, _)| golly == *turn)
+                    .ok_or("expected a turn of 1, 2, 4 or 8")?
+                    .2;
+            }
+            transitions
+        } else {
+            let nb_colours = spec.len();
+            let transitions = spec
+                .chars()
+                .enumerate()
+                .map(|(colour, c)| {
+                    let (_, _, turn) = TURNS
+                        .iter()
+                        .find(|&&(_, letter, _)| letter == c.to_ascii_uppercase())
+                        .ok_or("expected turns N, R, U or L")?;
+                    Ok((((colour + 1) % nb_colours) as u8, *turn, 0))
+                })
+                .collect::<Result<_, _>>()?;
+            vec![transitions]
+        };
+
+        let nb_states = transitions.len();
+        let nb_colours = transitions.first().map_or(0, Vec::len);
+        if !(2..=255).contains(&nb_colours) || nb_states > 255 {
+            return Err("expected 2 to 255 colours");
+        }
+        if transitions.iter().any(|colours| {
+            colours.len() != nb_colours
+                || colours.iter().any(|&(write, _, state)| {
+                    write as usize >= nb_colours || state as usize >= nb_states
+                })
+        }) {
+            return Err("expected the same colours for all states");
+        }
+
+        Ok(Turmite { transitions })
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// The turns alone, as in `RL`, when the ant has a single state and each colour becomes the next
+/// one, or else Golly's notation.
+impl fmt::Display for Turmite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let turn = |turn: u8| TURNS.iter().find(|&&(_, _, t)| t == turn).unwrap();
+
+        if self.is_ant() {
+            let turns: String = self.transitions[0]
+                .iter()
+                .map(|&(_, t, _)| turn(t).1)
+                .collect();
+            return write!(f, "{}", turns);
+        }
+
+        let states: Vec<_> = self
+            .transitions
+            .iter()
+            .map(|colours| {
+                let colours: Vec<_> = colours
+                    .iter()
+                    .map(|&(write, t, state)| format!("{{{},{},{}}}", write, turn(t).0, state))
+                    .collect();
+                format!("{{{}}}", colours.join(","))
+            })
+            .collect();
+        write!(f, "{{{}}}", states.join(","))
+    }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+/* --------------------------------------------------------------------------------------------- */
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let langton: Turmite = "RL".parse().unwrap();
+        assert_eq!("{{{1, 2, 0}, {0, 8, 0}}}".parse(), Ok(langton.clone()));
+        assert_eq!(langton.to_string(), "RL");
+        assert_eq!(langton.nb_colours(), 2);
+
+        let llrr: Turmite = "llrr".parse().unwrap();
+        assert_eq!(llrr.to_string(), "LLRR");
+        assert_eq!(llrr.nb_colours(), 4);
+
+        // Fibonacci spiral.
+        let fibonacci = "{{{1,8,1},{1,8,1}},{{1,2,1},{0,1,0}}}";
+        let turmite: Turmite = fibonacci.parse().unwrap();
+        assert_eq!(turmite.to_string(), fibonacci);
+
+        for invalid in [
+            "R",
+            "RX",
+            "{{{1,2,0},{0,8,0}}",
+            "{{{1,3,0},{0,8,0}}}",
+            "{{{1,2,0},{0,8,1}}}",
+            "{{{1,2,0},{2,8,0}}}",
+            "{{{1,2},{0,8,0}}}",
+            "{{{1,2,1},{0,8,0}},{{1,2,0}}}",
+        ] {
+            assert!(invalid.parse::<Turmite>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_next() {
+        let langton: Turmite = "RL".parse().unwrap();
+        let ant = Ant::new(RowCol { row: 5, col: 5 });
+
+        // Right on colour 0, left on colour 1, then one step forward.
+        let (write, next) = langton.next(&ant, 0);
+        assert_eq!(write, 1);
+        assert_eq!(next.position, RowCol { row: 5, col: 6 });
+        assert_eq!(next.heading, 1);
+
+        let (write, next) = langton.next(&ant, 1);
+        assert_eq!(write, 0);
+        assert_eq!(next.position, RowCol { row: 5, col: 4 });
+        assert_eq!(next.heading, 3);
+
+        assert_eq!(langton.next(&ant, 3), langton.next(&ant, 0));
+    }
+} // mod test
+
+/* --------------------------------------------------------------------------------------------- */
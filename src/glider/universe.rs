@@ -1,61 +1,349 @@
 /* --------------------------------------------------------------------------------------------- */
 
+use std::ops::Range;
+
 use super::grid::{Grid, RowCol};
+use super::hashlife::HashLife;
 use super::rule::Rule;
+use super::turmite::{Ant, Turmite};
 
 /* --------------------------------------------------------------------------------------------- */
 
 pub struct Universe<G> {
     pub generation: u64,
+    /// Live cells of `grid`, which are dead ones when `is_inverted`.
     pub live_cells: u64,
     pub grid: G,
     pub rule: Rule,
+    /// Ants of turmite rules, which start at the centre of the grid.
+    pub ants: Vec<Ant>,
+    // Grid of the previous generation, reused by `step` for the next one.
+    back: Option<G>,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+/// Cells which were born and cells which died at a generation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenerationDelta {
+    pub births: Vec<RowCol>,
+    pub deaths: Vec<RowCol>,
 }
 
 /* --------------------------------------------------------------------------------------------- */
 
 impl<G: Grid> Universe<G> {
+    /// Panics if generations of `rule` can't be computed on `grid` (see `check`).
     pub fn new(grid: G, rule: Rule) -> Self {
+        if let Err(e) = Self::check(&grid, &rule) {
+            panic!("{}", e);
+        }
+
         let live_cells = grid.count_live_cells();
+        let ants = match rule {
+            Rule::Turmite(_) => vec![Ant::new(RowCol {
+                row: grid.nb_rows() as i64 / 2,
+                col: grid.nb_columns() as i64 / 2,
+            })],
+            _ => vec![],
+        };
 
         Universe {
             generation: 0,
             live_cells,
             grid,
             rule,
+            ants,
+            back: None,
         }
     }
 
-    pub fn tick(&self) -> Self {
-        let mut next_grid = G::new(self.grid.nb_rows(), self.grid.nb_columns());
+    /// Why generations of `rule` can't be computed on `grid`, if they can't.
+    pub fn check(grid: &G, rule: &Rule) -> Result<(), &'static str> {
+        grid.check_rule(rule)?;
+
+        // Unlike life-like ones, rules where the background comes to life aren't emulated on the
+        // complement of unbounded grids.
+        match rule {
+            _ if grid.is_bounded() => Ok(()),
+            Rule::LargerThanLife(ltl) if ltl.birth.0 == 0 => {
+                Err("Larger than Life rules with B0 need a bounded grid")
+            }
+            Rule::Wolfram(wolfram) if wolfram.lives([false; 3]) => {
+                Err("Odd Wolfram rules need a bounded grid")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The next generation, this one being kept.
+    #[deprecated(note = "use `step`, which doesn't allocate a grid at each generation")]
+    #[allow(dead_code)]
+    pub fn tick(&self) -> Self
+    where
+        G: Clone,
+    {
+        let mut next = Universe {
+            generation: self.generation,
+            live_cells: self.live_cells,
+            grid: self.grid.clone(),
+            rule: self.rule.clone(),
+            ants: self.ants.clone(),
+            back: None,
+        };
+        next.step();
+
+        next
+    }
+
+    /// Computes the next generation in place: the grids of the current and previous generations
+    /// are swapped, so that no grid is allocated once both exist.
+    pub fn step(&mut self) {
+        self.step_n(1);
+    }
+
+    /// Steps `n` generations.
+    pub fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            let mut next_grid = self.take_back();
+            self.live_cells = self.tick_into(&mut next_grid);
+            self.ants = self.next_ants();
+            self.swap(next_grid);
+        }
+    }
+
+    /// Same as `step`, with cells split between `nb_threads` threads computing them in parallel.
+    pub fn par_step(&mut self, nb_threads: usize)
+    where
+        G: Sync,
+    {
+        let mut next_grid = self.take_back();
+        self.live_cells = self.par_tick_into(&mut next_grid, nb_threads);
+        self.ants = self.next_ants();
+        self.swap(next_grid);
+    }
+
+    /// Births and deaths of the last generation computed by `step`, `step_n` or `par_step`, found
+    /// in proportion to the population. None if the previous generation wasn't kept, or if the
+    /// background changed.
+    pub fn delta(&self) -> Option<GenerationDelta> {
+        let previous = self.back.as_ref()?;
+        let inverted = self.is_inverted();
+        if self.generation == 0 || self.is_inverted_at(self.generation - 1) != inverted {
+            return None;
+        }
+
+        let births = self.grid.live_cells().filter(|&rc| !previous.at(rc));
+        let deaths = previous.live_cells().filter(|&rc| !self.grid.at(rc));
+        let (births, deaths) = if inverted {
+            (deaths.collect(), births.collect())
+        } else {
+            (births.collect(), deaths.collect())
+        };
+
+        Some(GenerationDelta { births, deaths })
+    }
+
+    /// Whether the grid holds the complement of cells, for rules with B0 on unbounded grids (see
+    /// `Rule::at_generation`).
+    pub fn is_inverted(&self) -> bool {
+        self.is_inverted_at(self.generation)
+    }
+
+    /// Number of live cells, None if infinitely many cells of an unbounded grid are alive.
+    pub fn population(&self) -> Option<u64> {
+        (!self.is_inverted()).then_some(self.live_cells)
+    }
+
+    // Bounded grids compute B0 rules as they are (see `Rule::at_bounded_generation`).
+    fn is_inverted_at(&self, generation: u64) -> bool {
+        !self.grid.is_bounded() && self.rule.is_inverted(generation)
+    }
+
+    // The rule computing the next generation.
+    fn rule_at_generation(&self) -> Rule {
+        if self.grid.is_bounded() {
+            self.rule.at_bounded_generation(self.generation)
+        } else {
+            self.rule.at_generation(self.generation)
+        }
+    }
+
+    // The previous grid, emptied to receive the next generation.
+    fn take_back(&mut self) -> G {
+        match self.back.take() {
+            Some(mut grid) => {
+                grid.clear_like(&self.grid);
+                grid
+            }
+            None => self.grid.empty_like(),
+        }
+    }
+
+    fn swap(&mut self, next_grid: G) {
+        self.back = Some(std::mem::replace(&mut self.grid, next_grid));
+        self.generation += 1;
+    }
+
+    // Computes the next generation into an empty grid, and returns its number of live cells.
+    fn tick_into(&self, next_grid: &mut G) -> u64 {
+        let rule = self.rule_at_generation();
+        if let Rule::Turmite(turmite) = &rule {
+            return self.tick_turmite(turmite, next_grid);
+        }
+        if self.grid.next_generation(&rule, next_grid, 1) {
+            return next_grid.count_live_cells();
+        }
+
         let mut live_cells = 0;
 
-        for row in 0..self.grid.nb_rows() {
-            for col in 0..self.grid.nb_columns() {
-                if self.tick_cell(row, col) {
-                    live_cells += 1;
-                    next_grid.set(RowCol { row, col }, true);
-                }
+        for rc in self.grid.active_cells() {
+            let state = self.tick_state(&rule, rc);
+            if state != 0 {
+                live_cells += (state == 1) as u64;
+                next_grid.set_state(rc, state);
             }
         }
 
-        Universe {
-            generation: self.generation + 1,
-            live_cells,
-            grid: next_grid,
-            rule: self.rule,
+        live_cells
+    }
+
+    fn par_tick_into(&self, next_grid: &mut G, nb_threads: usize) -> u64
+    where
+        G: Sync,
+    {
+        let rule = self.rule_at_generation();
+        if let Rule::Turmite(turmite) = &rule {
+            return self.tick_turmite(turmite, next_grid);
         }
+        if self.grid.next_generation(&rule, next_grid, nb_threads) {
+            return next_grid.count_live_cells();
+        }
+
+        // Each thread computes a band of rows of the active area.
+        let (rows, _) = self.grid.active_area();
+        let nb_rows = (rows.end - rows.start).max(0) as usize;
+        let band_rows = usize::max(1, nb_rows.div_ceil(usize::max(1, nb_threads))) as i64;
+
+        let bands: Vec<Vec<(RowCol, u8)>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (rows.start..rows.end)
+                .step_by(band_rows as usize)
+                .map(|first_row| {
+                    let rule = &rule;
+                    let band = first_row..i64::min(first_row + band_rows, rows.end);
+                    scope.spawn(move || self.tick_band(rule, band))
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut live_cells = 0;
+
+        for (rc, state) in bands.into_iter().flatten() {
+            live_cells += (state == 1) as u64;
+            next_grid.set_state(rc, state);
+        }
+
+        live_cells
+    }
+
+    // Turmites only change the colour of the cells under the ants.
+    fn tick_turmite(&self, turmite: &Turmite, next_grid: &mut G) -> u64 {
+        for (rc, state) in self.grid.cell_states() {
+            next_grid.set_state(rc, state);
+        }
+        for ant in &self.ants {
+            let (colour, _) = turmite.next(ant, self.grid.state(ant.position));
+            next_grid.set_state(ant.position, colour);
+        }
+
+        next_grid.count_live_cells()
+    }
+
+    // Ants once they moved. They cross the borders of bounded grids as the topology tells, and
+    // leave planes.
+    fn next_ants(&self) -> Vec<Ant> {
+        let turmite = match &self.rule {
+            Rule::Turmite(turmite) => turmite,
+            _ => return vec![],
+        };
+        let (nb_rows, nb_cols) = (self.grid.nb_rows(), self.grid.nb_columns());
+
+        self.ants
+            .iter()
+            .filter_map(|ant| {
+                let mut ant = turmite.next(ant, self.grid.state(ant.position)).1;
+                if let Some(topology) = self.grid.topology() {
+                    let RowCol { row, col } = ant.position;
+                    let (row, col) = topology.wrap(nb_rows, nb_cols, row, col)?;
+                    ant.position = RowCol {
+                        row: row as i64,
+                        col: col as i64,
+                    };
+                }
+                Some(ant)
+            })
+            .collect()
+    }
+
+    /// Whether a cell is alive, complemented if the grid is.
+    #[allow(dead_code)]
+    pub fn at(&self, row: i64, col: i64) -> bool {
+        self.grid.at(RowCol { row, col }) != self.is_inverted()
     }
 
-    pub fn at(&self, row: usize, col: usize) -> bool {
-        self.grid.at(RowCol { row, col })
+    // Cells of `rows` alive or dying at the next generation, with their state.
+    fn tick_band(&self, rule: &Rule, rows: Range<i64>) -> Vec<(RowCol, u8)> {
+        self.grid
+            .active_cells_in(rows)
+            .map(|rc| (rc, self.tick_state(rule, rc)))
+            .filter(|&(_, state)| state != 0)
+            .collect()
+    }
+
+    fn tick_state(&self, rule: &Rule, rc: RowCol) -> u8 {
+        let state = self.grid.state(rc);
+
+        let lives = match rule {
+            Rule::LifeLike(life) if life.is_totalistic() => {
+                life.lives(state == 1, self.grid.count_live_neighbours(rc))
+            }
+            Rule::LifeLike(life) => life.lives_in(state == 1, self.grid.neighbourhood(rc)),
+            Rule::Table(rule_table) => {
+                return rule_table.next_state(state, &self.grid.neighbour_states(rc));
+            }
+            Rule::Margolus(margolus) => margolus.lives(rc, state == 1, self.grid.neighbourhood(rc)),
+            Rule::Wolfram(wolfram) if rc.row == wolfram.row() => {
+                let n = self.grid.neighbourhood(rc);
+                wolfram.lives([n & 1 != 0, n & 2 != 0, n & 4 != 0])
+            }
+            Rule::Wolfram(_) => return state,
+            _ => unreachable!("{} isn't computed cell by cell", rule),
+        };
+
+        rule.next_state(state, lives)
     }
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+impl Universe<HashLife> {
+    /// Jumps 2^k generations ahead in one step, k being at most `MAX_STEP`.
+    pub fn jump(&self, k: u8) -> Self {
+        let grid = match &self.rule {
+            Rule::LifeLike(life) => self.grid.step(life, k),
+            _ => panic!("HashLife only computes life-like rules"),
+        };
 
-    fn tick_cell(&self, row: usize, col: usize) -> bool {
-        self.rule.lives(
-            self.grid.at(RowCol { row, col }),
-            self.grid.count_live_neighbours(RowCol { row, col }),
-        )
+        Universe {
+            generation: self.generation + (1 << k),
+            live_cells: grid.count_live_cells(),
+            grid,
+            rule: self.rule.clone(),
+            ants: vec![],
+            back: None,
+        }
     }
 }
 
@@ -65,12 +353,29 @@ impl<G: Grid> Universe<G> {
 #[cfg(test)]
 mod test {
 
+    use std::io::BufReader;
+    use std::sync::Arc;
+
+    use crate::glider::bit_grid::BitGrid;
     use crate::glider::dense_grid::DenseGrid;
+    use crate::glider::grid::test::random_grid;
+    use crate::glider::grid::{Grid, RowCol, Topology, NEIGHBOURS};
+    use crate::glider::hashlife::HashLife;
+    use crate::glider::lenia_grid::LeniaGrid;
     use crate::glider::rle::{Rle, RleEntry};
     use crate::glider::rule::Rule;
-    use crate::glider::universe::Universe;
+    use crate::glider::rule_table::RuleTable;
+    use crate::glider::sparse_grid::SparseGrid;
+    use crate::glider::universe::{GenerationDelta, Universe};
+
+    impl<G: Grid> Universe<G> {
+        fn tick_cell(&self, row: i64, col: i64) -> bool {
+            self.tick_state(&self.rule, RowCol { row, col }) == 1
+        }
+    }
 
     #[test]
+    #[allow(deprecated)]
     fn test_tick() {
         let rle = Rle {
             pattern: vec![
@@ -94,6 +399,7 @@ mod test {
                 RleEntry::Live(2),
                 RleEntry::NewRow(1),
             ],
+            position: None,
         };
         let u = Universe::new(
             DenseGrid::new_from_rle(&rle, 5, 5),
@@ -122,6 +428,539 @@ mod test {
         assert_eq!(v.live_cells, 8); // 8 -3 (dying) +3 (spawning)
         assert_eq!(v.generation, 1);
     }
+
+    #[test]
+    fn test_par_step() {
+        let rows = 37;
+        let cols = 41;
+        let grid: DenseGrid = random_grid(7, rows, cols);
+
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        for nb_threads in [1, 2, 3, 8, 64] {
+            let mut sequential = Universe::new(grid.clone(), rule.clone());
+            let mut parallel = Universe::new(grid.clone(), rule.clone());
+
+            for _ in 0..10 {
+                sequential.step();
+                parallel.par_step(nb_threads);
+            }
+
+            assert_eq!(sequential.generation, parallel.generation);
+            assert_eq!(sequential.live_cells, parallel.live_cells);
+            for row in 0..rows as i64 {
+                for col in 0..cols as i64 {
+                    assert_eq!(sequential.at(row, col), parallel.at(row, col));
+                }
+            }
+        }
+
+        // Sparse patterns are computed outside the view as well.
+        let mut grid = SparseGrid::new(10, 10);
+        for &(row, col) in &[(-20, 30), (-20, 31), (-20, 32), (3, 3), (3, 4), (3, 5)] {
+            grid.set(RowCol { row, col }, true);
+        }
+        let mut u = Universe::new(grid, rule);
+        u.par_step(4);
+        assert_eq!(u.live_cells, 6);
+        assert!(u.at(-21, 31) && u.at(-19, 31) && !u.at(-20, 30));
+        assert!(u.at(2, 4) && u.at(4, 4));
+    }
+
+    #[test]
+    fn test_step() {
+        let rows = 45;
+        let cols = 50;
+        let grid: DenseGrid = random_grid(3, rows, cols);
+
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let mut ticked = Universe::new(grid.clone(), rule.clone());
+        let mut stepped = Universe::new(grid.clone(), rule.clone());
+        let mut sparse = SparseGrid::new(rows, cols);
+        for row in 0..rows as i64 {
+            for col in 0..cols as i64 {
+                sparse.set(RowCol { row, col }, grid.at(RowCol { row, col }));
+            }
+        }
+        let mut sparse = Universe::new(sparse, rule);
+
+        for i in 0..30 {
+            ticked.step();
+            if i % 2 == 0 {
+                stepped.step();
+            } else {
+                stepped.par_step(3);
+            }
+        }
+        sparse.step_n(30);
+
+        assert_eq!(stepped.generation, 30);
+        assert_eq!(sparse.generation, 30);
+        assert_eq!(stepped.live_cells, ticked.live_cells);
+        assert_eq!(sparse.live_cells, ticked.live_cells);
+        let (rows, cols) = ticked.grid.active_area();
+        for row in rows {
+            for col in cols.clone() {
+                assert_eq!(stepped.at(row, col), ticked.at(row, col));
+                assert_eq!(sparse.at(row, col), ticked.at(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta() {
+        // Blinker.
+        let mut grid = SparseGrid::new(5, 5);
+        for col in 1..4 {
+            grid.set(RowCol { row: 2, col }, true);
+        }
+        let mut u = Universe::new(grid, Rule::new(vec![3], vec![2, 3]));
+        assert_eq!(u.delta(), None);
+
+        u.step();
+        let mut delta = u.delta().unwrap();
+        delta.births.sort_by_key(|rc| rc.row);
+        delta.deaths.sort_by_key(|rc| rc.col);
+        assert_eq!(
+            delta,
+            GenerationDelta {
+                births: vec![RowCol { row: 1, col: 2 }, RowCol { row: 3, col: 2 }],
+                deaths: vec![RowCol { row: 2, col: 1 }, RowCol { row: 2, col: 3 }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_non_totalistic() {
+        let size = 24;
+        let mut grid = SparseGrid::new(size, size);
+        let mut rotated = SparseGrid::new(size, size);
+
+        for RowCol { row, col } in random_grid::<SparseGrid>(5, 8, 8).live_cells() {
+            let (row, col) = (row + 8, col + 8);
+            grid.set(RowCol { row, col }, true);
+            rotated.set(
+                RowCol {
+                    row: col,
+                    col: -row,
+                },
+                true,
+            );
+        }
+
+        let rule: Rule = "B2-a3-cnqy/S12-e4ity".parse().unwrap();
+        let mut sparse = Universe::new(grid.clone(), rule.clone());
+        let mut rotated = Universe::new(rotated, rule.clone());
+        let mut dense = Universe::new(DenseGrid::new(size, size), rule);
+        for rc in grid.live_cells() {
+            dense.grid.set(rc, true);
+        }
+
+        for _ in 0..12 {
+            sparse.step();
+            rotated.step();
+            dense.step();
+
+            assert_eq!(sparse.live_cells, rotated.live_cells);
+            assert_eq!(sparse.live_cells, dense.live_cells);
+            for RowCol { row, col } in sparse.grid.live_cells() {
+                assert!(rotated.at(col, -row));
+                assert!(dense.at(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generations() {
+        let rule: Rule = "B2/S/C3".parse().unwrap();
+        let mut sparse = SparseGrid::new(10, 10);
+        sparse.set(RowCol { row: 5, col: 5 }, true);
+        sparse.set(RowCol { row: 5, col: 6 }, true);
+        let mut dense = Universe::new(DenseGrid::new(10, 10), rule.clone());
+        for rc in sparse.live_cells() {
+            dense.grid.set(rc, true);
+        }
+        let mut sparse = Universe::new(sparse, rule);
+
+        sparse.step();
+        dense.step();
+        let mut cells: Vec<_> = sparse.grid.cell_states().collect();
+        cells.sort_by_key(|&(rc, _)| (rc.row, rc.col));
+        assert_eq!(
+            cells,
+            [
+                (4, 5, 1),
+                (4, 6, 1),
+                (5, 5, 2),
+                (5, 6, 2),
+                (6, 5, 1),
+                (6, 6, 1)
+            ]
+            .map(|(row, col, state)| (RowCol { row, col }, state))
+        );
+        assert_eq!(sparse.live_cells, 4);
+
+        for _ in 0..10 {
+            let mut sparse_cells: Vec<_> = sparse.grid.cell_states().collect();
+            let mut dense_cells: Vec<_> = dense.grid.cell_states().collect();
+            sparse_cells.sort_by_key(|&(rc, _)| (rc.row, rc.col));
+            dense_cells.sort_by_key(|&(rc, _)| (rc.row, rc.col));
+
+            assert_eq!(sparse_cells, dense_cells);
+            assert_eq!(sparse.live_cells, dense.live_cells);
+            sparse.step();
+            dense.step();
+        }
+    }
+
+    #[test]
+    fn test_sparse_tick() {
+        // bo$2bo$3o!
+        let rle = Rle {
+            pattern: vec![
+                RleEntry::Dead(1),
+                RleEntry::Live(1),
+                RleEntry::NewRow(1),
+                RleEntry::Dead(2),
+                RleEntry::Live(1),
+                RleEntry::NewRow(1),
+                RleEntry::Live(3),
+            ],
+            position: None,
+        };
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let mut dense = Universe::new(DenseGrid::new_from_rle(&rle, 20, 20), rule.clone());
+        let mut sparse = Universe::new(SparseGrid::new_from_rle(&rle, 20, 20), rule);
+
+        for _ in 0..20 {
+            dense.step();
+            sparse.step();
+        }
+
+        assert_eq!(sparse.live_cells, 5);
+        for row in 0..20 {
+            for col in 0..20 {
+                assert_eq!(dense.at(row, col), sparse.at(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rule_table() {
+        // Variables are bound, hence the sets for neighbours which may differ.
+        let (any, other) = ("{0,1,2,3}", "{0,2,3}");
+        let wireworld = format!(
+            "@RULE WireWorld\n@TABLE\nn_states:4\nsymmetries:permute\n\
+             1,{0},2\n2,{0},3\n3,1,1,{1},1\n3,1,{2},1\n",
+            [any; 8].join(","),
+            [other; 6].join(","),
+            [other; 7].join(","),
+        );
+        let rule = Rule::Table(Arc::new(
+            RuleTable::read(BufReader::new(wireworld.as_bytes())).unwrap(),
+        ));
+        assert_eq!(rule.to_string(), "WireWorld");
+
+        // An electron, its tail on the left, running along a wire.
+        let mut dense = DenseGrid::new(3, 10);
+        let mut sparse = SparseGrid::new(3, 10);
+        for col in 0..10 {
+            let state = [2, 1].get(col as usize).copied().unwrap_or(3);
+            dense.set_state(RowCol { row: 1, col }, state);
+            sparse.set_state(RowCol { row: 1, col }, state);
+        }
+
+        let mut dense = Universe::new(dense, rule.clone());
+        let mut sparse = Universe::new(sparse, rule);
+        for generation in 1..8 {
+            dense.step();
+            sparse.step();
+            for col in -1..11 {
+                let rc = RowCol { row: 1, col };
+                let expected = match col - generation {
+                    _ if !(0..10).contains(&col) => 0,
+                    1 => 1,
+                    0 => 2,
+                    _ => 3,
+                };
+                assert_eq!(dense.grid.state(rc), expected);
+                assert_eq!(sparse.grid.state(rc), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_b0() {
+        let (rows, cols) = (12, 14);
+        let random: SparseGrid = random_grid(5, rows, cols);
+        let cells: Vec<Vec<bool>> = (0..rows as i64)
+            .map(|row| {
+                (0..cols as i64)
+                    .map(|col| random.at(RowCol { row, col }))
+                    .collect()
+            })
+            .collect();
+
+        // B0 rules with the background alternating or staying alive, on bounded grids where they
+        // are computed directly, cells beyond the borders of planes being dead.
+        for (rule, topology) in [
+            ("B0/S2", Topology::Torus),
+            ("B013/S2368", Topology::Torus),
+            ("B0/S2", Topology::Plane),
+            ("B013/S2368", Topology::Plane),
+        ] {
+            let rule: Rule = rule.parse().unwrap();
+            let life = match rule {
+                Rule::LifeLike(life) => life,
+                _ => unreachable!(),
+            };
+            let mut grid = DenseGrid::new(rows, cols).with_topology(topology);
+            for (row, cells) in cells.iter().enumerate() {
+                for (col, &cell) in cells.iter().enumerate() {
+                    grid.set(
+                        RowCol {
+                            row: row as i64,
+                            col: col as i64,
+                        },
+                        cell,
+                    );
+                }
+            }
+            let mut u = Universe::new(grid, rule);
+            let mut expected = cells.clone();
+
+            for _ in 0..10 {
+                u.step();
+                expected = (0..rows)
+                    .map(|row| {
+                        (0..cols)
+                            .map(|col| {
+                                let count = NEIGHBOURS
+                                    .iter()
+                                    .filter(|&&(dr, dc)| {
+                                        let (r, c) = (row as i64 + dr, col as i64 + dc);
+                                        topology
+                                            .wrap(rows, cols, r, c)
+                                            .is_some_and(|(r, c)| expected[r][c])
+                                    })
+                                    .count();
+                                life.lives(expected[row][col], count as u8)
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                for (row, cells) in expected.iter().enumerate() {
+                    for (col, &cell) in cells.iter().enumerate() {
+                        assert_eq!(u.at(row as i64, col as i64), cell);
+                    }
+                }
+                let population = expected.iter().flatten().filter(|&&c| c).count();
+                assert_eq!(u.population(), Some(population as u64));
+            }
+        }
+
+        // Corners of a plane have 3 neighbours once all cells are born.
+        fn corners<G: Grid>(grid: G) {
+            let mut u = Universe::new(grid, "B0/S3".parse().unwrap());
+            u.step_n(2);
+            assert_eq!(u.population(), Some(4));
+            assert!(u.at(0, 0) && u.at(2, 2) && !u.at(1, 1));
+        }
+        corners(DenseGrid::new(3, 3).with_topology(Topology::Plane));
+        corners(BitGrid::new(3, 3));
+
+        // The background of unbounded grids is alive at odd generations.
+        let mut u = Universe::new(SparseGrid::new(5, 5), "B0/S".parse().unwrap());
+        u.step();
+        assert!(u.at(100, -100));
+        assert_eq!(u.population(), None);
+        assert_eq!(u.delta(), None);
+        u.step();
+        assert_eq!(u.population(), Some(0));
+    }
+
+    #[test]
+    fn test_margolus() {
+        let rule: Rule = "BBM".parse().unwrap();
+        let ball = RowCol { row: 0, col: 0 };
+
+        // A lone ball crosses its block at each generation, blocks alternating.
+        let mut grid = DenseGrid::new(10, 10);
+        grid.set(ball, true);
+        let mut dense = Universe::new(grid, rule.clone());
+        let mut grid = SparseGrid::new(10, 10);
+        grid.set(ball, true);
+        let mut sparse = Universe::new(grid, rule);
+        dense.step_n(4);
+        sparse.step_n(4);
+        assert_eq!(
+            dense.grid.live_cells().collect::<Vec<_>>(),
+            vec![RowCol { row: 4, col: 4 }]
+        );
+        assert_eq!(
+            sparse.grid.live_cells().collect::<Vec<_>>(),
+            vec![RowCol { row: 4, col: 4 }]
+        );
+
+        // Tron flips empty blocks, the background included.
+        let mut grid = SparseGrid::new(10, 10);
+        grid.set(ball, true);
+        let mut u = Universe::new(grid, "Tron".parse().unwrap());
+        u.step();
+        assert!(u.at(0, 0) && u.at(50, -7));
+        assert!(!u.at(0, 1) && !u.at(1, 0) && !u.at(1, 1));
+        u.step();
+        assert!(!u.at(50, -7));
+        assert_eq!(u.population(), Some(u.live_cells));
+    }
+
+    #[test]
+    fn test_wolfram() {
+        let rule: Rule = "W30".parse().unwrap();
+        let expected = ["...#...", "..###..", ".##..#.", "##.####"];
+        let check = |at: &dyn Fn(i64, i64) -> bool| {
+            for (row, cells) in expected.iter().enumerate() {
+                for (col, cell) in cells.chars().enumerate() {
+                    assert_eq!(
+                        at(row as i64, col as i64 + 2),
+                        cell == '#',
+                        "{} {}",
+                        row,
+                        col
+                    );
+                }
+            }
+        };
+        let seed = RowCol { row: 0, col: 5 };
+
+        let mut grid = DenseGrid::new(10, 10);
+        grid.set(seed, true);
+        let mut dense = Universe::new(grid, rule.clone());
+        dense.step_n(3);
+        check(&|row, col| dense.at(row, col));
+
+        let mut grid = SparseGrid::new(10, 10);
+        grid.set(seed, true);
+        let mut sparse = Universe::new(grid, rule.clone());
+        sparse.step_n(3);
+        check(&|row, col| sparse.at(row, col));
+
+        // On a torus, the last row is followed by the first one.
+        let mut grid = DenseGrid::new(3, 10).with_topology(Topology::Torus);
+        grid.set(seed, true);
+        let mut torus = Universe::new(grid, rule.clone());
+        torus.step_n(3);
+        assert_eq!(torus.live_cells, 3 + 3 + 6);
+        assert!(!torus.at(0, 4) && torus.at(0, 3));
+
+        // Grids reused for the next generation give the same rows as new ones.
+        for topology in [None, Some(Topology::Torus), Some(Topology::Sphere)] {
+            let mut grid = DenseGrid::new(7, 7);
+            grid.set(RowCol { row: 0, col: 3 }, true);
+            let grid = topology.map_or(grid.clone(), |topology| grid.with_topology(topology));
+            let mut reused = Universe::new(grid.clone(), rule.clone());
+            let mut new = grid;
+            for generation in 0..20 {
+                reused.step();
+                let mut next = new.empty_like();
+                assert!(new.next_generation(&rule.at_generation(generation), &mut next, 1));
+                new = next;
+
+                assert_eq!(reused.live_cells, new.count_live_cells());
+                for row in -1..25 {
+                    for col in -12..20 {
+                        let rc = RowCol { row, col };
+                        assert_eq!(reused.grid.at(rc), new.at(rc), "{:?}", topology);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let ltl: Rule = "R1,C0,M0,S2..3,B3..3,NM".parse().unwrap();
+        assert!(Universe::check(&DenseGrid::new(5, 5), &ltl).is_ok());
+        assert!(Universe::check(&SparseGrid::new(5, 5), &ltl).is_err());
+        assert!(Universe::check(&BitGrid::new(5, 5), &ltl).is_err());
+
+        let b0: Rule = "R1,C0,M0,S2..3,B0..3,NM".parse().unwrap();
+        let torus = DenseGrid::new(5, 5).with_topology(Topology::Torus);
+        assert!(Universe::check(&DenseGrid::new(5, 5), &b0).is_err());
+        assert!(Universe::check(&torus, &b0).is_ok());
+
+        let lenia: Rule = "Lenia(R3,T10,m0.15,s0.015)".parse().unwrap();
+        assert!(Universe::check(&LeniaGrid::new(5, 5), &lenia).is_ok());
+        assert!(Universe::check(&DenseGrid::new(5, 5), &lenia).is_err());
+        assert!(Universe::check(&SparseGrid::new(5, 5), &lenia).is_err());
+        assert!(Universe::check(&LeniaGrid::new(5, 5), &ltl).is_err());
+
+        // W1 turns the empty background into live cells.
+        let odd: Rule = "W1".parse().unwrap();
+        assert!(Universe::check(&DenseGrid::new(5, 5), &odd).is_err());
+        assert!(Universe::check(&SparseGrid::new(5, 5), &odd).is_err());
+        assert!(Universe::check(&torus, &odd).is_ok());
+        assert!(Universe::check(&SparseGrid::new(5, 5), &"W30".parse().unwrap()).is_ok());
+
+        let hashlife = HashLife::new(8, 8);
+        assert!(Universe::check(&hashlife, &"B36/S23".parse().unwrap()).is_ok());
+        for rule in ["B0/S2", "B2/S/C3", "BBM", "RL", "R1,C0,M0,S2..3,B3..3,NM"] {
+            assert!(Universe::check(&hashlife, &rule.parse().unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_turmite() {
+        let rule: Rule = "Langton's Ant".parse().unwrap();
+        let start = RowCol { row: 5, col: 5 };
+
+        // Turning right on empty cells, the ant walks around a square back to its start, then
+        // turns left there.
+        let mut dense = Universe::new(DenseGrid::new(10, 10), rule.clone());
+        let mut sparse = Universe::new(SparseGrid::new(10, 10), rule.clone());
+        for ants in [&dense.ants, &sparse.ants] {
+            assert_eq!(ants[0].position, start);
+        }
+        dense.step_n(4);
+        sparse.step_n(4);
+        assert_eq!(dense.live_cells, 4);
+        assert_eq!(sparse.live_cells, 4);
+        assert_eq!(dense.ants, sparse.ants);
+        assert_eq!(dense.ants[0].position, start);
+        assert_eq!(dense.ants[0].heading, 0);
+
+        dense.step();
+        assert_eq!(dense.live_cells, 3);
+        assert!(!dense.at(5, 5));
+        assert_eq!(dense.ants[0].position, RowCol { row: 5, col: 4 });
+
+        // Ants leave planes.
+        let mut u = Universe::new(
+            DenseGrid::new(1, 1).with_topology(Topology::Plane),
+            rule.clone(),
+        );
+        u.step();
+        assert!(u.ants.is_empty());
+        assert_eq!(u.live_cells, 1);
+
+        // Going east from the last column of a torus, the ant comes back on the first one.
+        let mut u = Universe::new(DenseGrid::new(2, 2).with_topology(Topology::Torus), rule);
+        assert_eq!(u.ants[0].position, RowCol { row: 1, col: 1 });
+        u.step();
+        assert_eq!(u.ants[0].position, RowCol { row: 1, col: 0 });
+        assert_eq!(u.ants[0].heading, 1);
+        u.step();
+        assert_eq!(u.ants[0].position, RowCol { row: 0, col: 0 });
+        assert_eq!(u.live_cells, 2);
+
+        // A cell of a colour the ant doesn't know is of colour 0.
+        let data = "x = 1, y = 1, rule = RL\nC!\n";
+        let (rle, rule, _) = Rle::read(BufReader::new(data.as_bytes())).unwrap();
+        let mut u = Universe::new(DenseGrid::new_from_rle(&rle, 1, 1), rule);
+        u.step();
+        assert_eq!(u.ants[0].heading, 1);
+        assert_eq!(u.grid.state(RowCol { row: 0, col: 0 }), 1);
+    }
 }
 
 /* --------------------------------------------------------------------------------------------- */
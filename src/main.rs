@@ -2,15 +2,137 @@ use std::fs::File;
 use std::io::BufReader;
 
 mod glider;
-use clap::Parser;
+use clap::{value_parser, Parser, ValueEnum};
+use glider::bit_grid::BitGrid;
 use glider::dense_grid::DenseGrid;
+use glider::grid::{Grid, RowCol, Topology};
+use glider::hashlife::{HashLife, MAX_STEP};
+use glider::lenia_grid::LeniaGrid;
 use glider::render;
 use glider::rle::Rle;
+use glider::rule::Rule;
+use glider::sparse_grid::SparseGrid;
 use glider::universe::Universe;
 
+#[derive(Clone, Copy, ValueEnum)]
+enum Backend {
+    Dense,
+    Sparse,
+    Bits,
+    Hashlife,
+}
+
 #[derive(Parser)]
 struct Cli {
     rle_file: Option<String>,
+
+    /// Rule replacing the one of the RLE file, as a rulestring or a name such as HighLife
+    #[arg(long)]
+    rule: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = Backend::Dense)]
+    grid: Backend,
+
+    /// Join opposite borders (dense only)
+    #[arg(long)]
+    torus: bool,
+
+    /// Number of threads computing a generation (dense only)
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+
+    /// Advance 2^STEP generations at each frame (hashlife only)
+    #[arg(long, default_value_t = 0, value_parser = value_parser!(u8).range(..=MAX_STEP as i64))]
+    step: u8,
+}
+
+/* --------------------------------------------------------------------------------------------- */
+
+const GRID_ROWS: usize = 1000;
+const GRID_COLS: usize = 1000;
+const LENIA_ROWS: usize = 250;
+const LENIA_COLS: usize = 250;
+
+// One-dimensional rules start from the top row, each row below being the next generation.
+fn at_top(rle: Rle, nb_rows: usize) -> Rle {
+    let (rle_rows, rle_cols) = rle.dimension();
+    let col = rle
+        .position
+        .map_or(-(rle_cols as i64 / 2), |position| position.col);
+
+    Rle {
+        position: Some(RowCol {
+            row: -(usize::max(nb_rows, rle_rows) as i64 / 2),
+            col,
+        }),
+        ..rle
+    }
+}
+
+// Why a rule can't run on `grid`, if it can't. `bounded_grid` is the topology of the rule's bounded
+// grid suffix, if any, which is another way to bound the grid than `--torus`.
+fn check(
+    rule: &Rule,
+    grid: Backend,
+    torus: bool,
+    bounded_grid: Option<Topology>,
+) -> Result<(), &'static str> {
+    let dense = matches!(grid, Backend::Dense);
+
+    if bounded_grid.is_some() && !dense {
+        return Err("Rules with a bounded grid need a dense grid");
+    }
+    if bounded_grid.is_some() && torus {
+        return Err("Rules with a bounded grid already have a topology");
+    }
+    if torus && !dense {
+        return Err("--torus needs a dense grid");
+    }
+
+    if let Rule::Lenia(_) = rule {
+        if !dense {
+            return Err("Lenia rules have their own grid");
+        }
+        if !matches!(bounded_grid, None | Some(Topology::Torus)) {
+            return Err("Lenia rules only run on tori");
+        }
+    }
+
+    if matches!(rule, Rule::Wolfram(_)) && !matches!(grid, Backend::Dense | Backend::Sparse) {
+        return Err("Wolfram rules need a dense or sparse grid");
+    }
+
+    if rule.nb_states() > 2 && matches!(grid, Backend::Bits | Backend::Hashlife) {
+        return Err("Rules with more than 2 states need a dense or sparse grid");
+    }
+
+    if rule.is_inverted(1) && matches!(grid, Backend::Hashlife) {
+        return Err("B0 rules need a dense, sparse or bits grid");
+    }
+
+    if matches!(rule, Rule::Table(_)) && matches!(grid, Backend::Bits | Backend::Hashlife) {
+        return Err("Rules of .rule files need a dense or sparse grid");
+    }
+
+    if matches!(rule, Rule::Turmite(_)) && matches!(grid, Backend::Hashlife) {
+        return Err("Turmites need a dense, sparse or bits grid");
+    }
+
+    if matches!(rule, Rule::Margolus(_)) && matches!(grid, Backend::Hashlife) {
+        return Err("Block rules need a dense, sparse or bits grid");
+    }
+
+    Ok(())
+}
+
+fn run<G: Grid>(grid: G, rule: Rule, step: impl FnMut(&mut Universe<G>)) {
+    if let Err(e) = Universe::check(&grid, &rule) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let u = Universe::new(grid, rule);
+
+    render::render_universe(u, step);
 }
 
 /* --------------------------------------------------------------------------------------------- */
@@ -19,15 +141,77 @@ fn main() {
     let cli = Cli::parse();
 
     let file = File::open(cli.rle_file.unwrap()).unwrap();
-    let (rle, rule) = Rle::read(BufReader::new(file)).unwrap();
+    let (rle, rule, bounded_grid) = Rle::read(BufReader::new(file)).unwrap();
+    let rule = match cli.rule {
+        Some(rule) => Rule::read(&rule).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+        None => rule,
+    };
 
-    let grid_rows = 1000;
-    let grid_cols = 1000;
+    if let Err(e) = check(
+        &rule,
+        cli.grid,
+        cli.torus,
+        bounded_grid.map(|bounds| bounds.topology),
+    ) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
-    let grid = DenseGrid::new_from_rle(&rle, grid_rows, grid_cols);
-    let u = Universe::new(grid, rule);
+    // Lenia rules have their own grid, a torus of the bounded grid's dimensions if there is one.
+    if let Rule::Lenia(_) = rule {
+        let (rows, cols) = bounded_grid.map_or((LENIA_ROWS, LENIA_COLS), |bounds| {
+            (bounds.nb_rows, bounds.nb_cols)
+        });
+        let threads = cli.threads;
+        return run(LeniaGrid::new_from_rle(&rle, rows, cols), rule, move |u| {
+            u.par_step(threads)
+        });
+    }
+
+    let rle = match (&rule, bounded_grid) {
+        (Rule::Wolfram(_), Some(bounds)) => at_top(rle, bounds.nb_rows),
+        (Rule::Wolfram(_), None) => at_top(rle, GRID_ROWS),
+        _ => rle,
+    };
+
+    if let Some(bounds) = bounded_grid {
+        let grid = DenseGrid::new_from_rle(&rle, bounds.nb_rows, bounds.nb_cols)
+            .with_topology(bounds.topology);
+        let threads = cli.threads;
+        return run(grid, rule, move |u| u.par_step(threads));
+    }
 
-    render::render_universe(u);
+    let step = cli.step;
+    match cli.grid {
+        Backend::Dense => {
+            let grid = DenseGrid::new_from_rle(&rle, GRID_ROWS, GRID_COLS);
+            let grid = if cli.torus {
+                grid.with_topology(Topology::Torus)
+            } else {
+                grid
+            };
+            let threads = cli.threads;
+            run(grid, rule, move |u| u.par_step(threads))
+        }
+        Backend::Sparse => run(
+            SparseGrid::new_from_rle(&rle, GRID_ROWS, GRID_COLS),
+            rule,
+            Universe::step,
+        ),
+        Backend::Bits => run(
+            BitGrid::new_from_rle(&rle, GRID_ROWS, GRID_COLS),
+            rule,
+            Universe::step,
+        ),
+        Backend::Hashlife => run(
+            HashLife::new_from_rle(&rle, GRID_ROWS, GRID_COLS),
+            rule,
+            move |u| *u = u.jump(step),
+        ),
+    }
 }
 
 /* --------------------------------------------------------------------------------------------- */